@@ -0,0 +1,264 @@
+//! The controller's commands, each encoding its own parameter bytes.
+//!
+//! These are what [crate::Display] sends internally, and can be sent directly with
+//! [crate::Display::send] to experiment with registers the driver does not expose. The display
+//! does not know about commands sent that way, so it may undo them or be confused by them.
+
+use crate::{Border, Temperature};
+
+/// One of the controller's two RAMs.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Ram {
+    /// The black and white RAM (written with 0x24), which holds the image to show.
+    BlackWhite,
+    /// The red RAM (written with 0x26), which holds the previous image on black and white panels
+    /// and the red plane on tri-color panels.
+    Red,
+}
+
+/// How the controller reads one of its RAMs during display updates, set with
+/// [crate::Display::set_ram_options]. The RAM itself is left as it is.
+#[repr(u8)]
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum RamOption {
+    /// Use the RAM as it is.
+    #[default]
+    Normal,
+    /// Ignore the RAM and read every pixel as 0.
+    Bypass,
+    /// Read every pixel inverted.
+    Inverse,
+}
+
+impl RamOption {
+    /// Returns the option's half of the first display update control 1 (0x21) parameter.
+    pub(crate) fn bits(self) -> u8 {
+        match self {
+            RamOption::Normal => 0x0,
+            RamOption::Bypass => 0x4,
+            RamOption::Inverse => 0x8,
+        }
+    }
+}
+
+/// The deep sleep mode (0x10).
+#[repr(u8)]
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum DeepSleep {
+    /// Normal operation.
+    #[default]
+    Off = 0x00,
+    /// Deep sleep that keeps the RAM contents.
+    Mode1 = 0x01,
+    /// Deep sleep that loses the RAM contents, for the lowest current.
+    Mode2 = 0x03,
+}
+
+/// The direction the address counters move in after each byte written to RAM (0x11), always
+/// along X first.
+#[repr(u8)]
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum DataEntryMode {
+    DecrementXY = 0x00,
+    IncrementXDecrementY = 0x01,
+    DecrementXIncrementY = 0x02,
+    #[default]
+    IncrementXY = 0x03,
+}
+
+/// A command with its parameters.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Command<'a> {
+    /// 0x01: the number of gate lines in use, minus one, and the order they are scanned in.
+    DriverOutputControl { last_gate: u16, scanning: u8 },
+    /// 0x03: the gate driving voltage.
+    GateVoltage(u8),
+    /// 0x04: the source driving voltages VSH1, VSH2 and VSL.
+    SourceVoltage([u8; 3]),
+    /// 0x10: the deep sleep mode.
+    DeepSleep(DeepSleep),
+    /// 0x11: the direction the address counters move in after each byte written to RAM.
+    DataEntryMode(DataEntryMode),
+    /// 0x14: checks whether the booster's voltages are ready, with the cool down duration and
+    /// number of loops. The result is in [Status::hv_not_ready].
+    HvReadyDetection(u8),
+    /// 0x15: checks the supply voltage against a detection level. The result is in
+    /// [Status::vci_low].
+    VciDetection(u8),
+    /// 0x18: which temperature sensor to use, where 0x80 is the internal one.
+    TemperatureSensor(u8),
+    /// 0x1A: the temperature used to pick waveforms, instead of the sensor's reading.
+    WriteTemperature(Temperature),
+    /// 0x1B: reads back the temperature register.
+    ReadTemperature,
+    /// 0x20: runs the display update sequence set with [Command::DisplayUpdateControl2].
+    MasterActivation,
+    /// 0x21: the RAM content options used during display updates, and the source output mode.
+    DisplayUpdateControl1([u8; 2]),
+    /// 0x22: the steps run by [Command::MasterActivation].
+    DisplayUpdateControl2(u8),
+    /// 0x24 or 0x26: writes bytes to RAM at the address counters.
+    WriteRam(Ram, &'a [u8]),
+    /// 0x27: reads back the RAM chosen with [Command::RamReadOption], after a dummy byte.
+    ReadRam,
+    /// 0x2D: reads back the display options programmed into OTP.
+    ReadDisplayOption,
+    /// 0x2E: reads back the user ID programmed into OTP.
+    ReadUserId,
+    /// 0x2F: reads back the status register, see [Status].
+    ReadStatus,
+    /// 0x2C: the VCOM voltage.
+    WriteVcom(u8),
+    /// 0x32: the waveform lookup table.
+    WriteLut(&'a [u8; 153]),
+    /// 0x3C: the waveform used for the border.
+    BorderWaveform(Border),
+    /// 0x3F: the end option byte that goes with a custom lookup table.
+    EndOption(u8),
+    /// 0x41: which RAM is read back with 0x27.
+    RamReadOption(Ram),
+    /// 0x44: the first and last column of the RAM window, in bytes of 8 pixels.
+    RamXAddress { start: u8, end: u8 },
+    /// 0x45: the first and last row of the RAM window.
+    RamYAddress { start: u16, end: u16 },
+    /// 0x4E: the column address counter, in bytes of 8 pixels.
+    RamXCounter(u8),
+    /// 0x4F: the row address counter.
+    RamYCounter(u16),
+    /// Any other command, with its parameters as they are sent.
+    Other(u8, &'a [u8]),
+}
+
+impl<'a> Command<'a> {
+    /// Returns the command byte.
+    pub fn code(&self) -> u8 {
+        match self {
+            Command::DriverOutputControl { .. } => 0x01,
+            Command::GateVoltage(_) => 0x03,
+            Command::SourceVoltage(_) => 0x04,
+            Command::DeepSleep(_) => 0x10,
+            Command::DataEntryMode(_) => 0x11,
+            Command::HvReadyDetection(_) => 0x14,
+            Command::VciDetection(_) => 0x15,
+            Command::TemperatureSensor(_) => 0x18,
+            Command::WriteTemperature(_) => 0x1a,
+            Command::ReadTemperature => 0x1b,
+            Command::MasterActivation => 0x20,
+            Command::DisplayUpdateControl1(_) => 0x21,
+            Command::DisplayUpdateControl2(_) => 0x22,
+            Command::WriteRam(Ram::BlackWhite, _) => 0x24,
+            Command::WriteRam(Ram::Red, _) => 0x26,
+            Command::ReadRam => 0x27,
+            Command::ReadDisplayOption => 0x2d,
+            Command::ReadUserId => 0x2e,
+            Command::ReadStatus => 0x2f,
+            Command::WriteVcom(_) => 0x2c,
+            Command::WriteLut(_) => 0x32,
+            Command::BorderWaveform(_) => 0x3c,
+            Command::EndOption(_) => 0x3f,
+            Command::RamReadOption(_) => 0x41,
+            Command::RamXAddress { .. } => 0x44,
+            Command::RamYAddress { .. } => 0x45,
+            Command::RamXCounter(_) => 0x4e,
+            Command::RamYCounter(_) => 0x4f,
+            Command::Other(code, _) => *code,
+        }
+    }
+
+    /// Returns the parameter bytes, encoding them into `buf` if they are not borrowed.
+    pub fn params<'b>(&'b self, buf: &'b mut [u8; 4]) -> &'b [u8] {
+        let len = match *self {
+            Command::WriteRam(_, data) | Command::Other(_, data) => return data,
+            Command::WriteLut(lut) => return lut,
+            Command::SourceVoltage(ref voltages) => return voltages,
+            Command::DisplayUpdateControl1(ref options) => return options,
+            Command::MasterActivation
+            | Command::ReadTemperature
+            | Command::ReadRam
+            | Command::ReadDisplayOption
+            | Command::ReadUserId
+            | Command::ReadStatus => 0,
+            Command::GateVoltage(byte)
+            | Command::HvReadyDetection(byte)
+            | Command::VciDetection(byte)
+            | Command::TemperatureSensor(byte)
+            | Command::DisplayUpdateControl2(byte)
+            | Command::WriteVcom(byte)
+            | Command::EndOption(byte)
+            | Command::RamXCounter(byte) => {
+                buf[0] = byte;
+                1
+            }
+            Command::DeepSleep(mode) => {
+                buf[0] = mode as u8;
+                1
+            }
+            Command::DataEntryMode(mode) => {
+                buf[0] = mode as u8;
+                1
+            }
+            Command::BorderWaveform(border) => {
+                buf[0] = border.waveform();
+                1
+            }
+            Command::RamReadOption(ram) => {
+                buf[0] = (ram == Ram::Red) as u8;
+                1
+            }
+            Command::WriteTemperature(temperature) => {
+                buf[..2].copy_from_slice(&temperature.to_register());
+                2
+            }
+            Command::RamYCounter(y) => {
+                buf[..2].copy_from_slice(&y.to_le_bytes());
+                2
+            }
+            Command::RamXAddress { start, end } => {
+                buf[..2].copy_from_slice(&[start, end]);
+                2
+            }
+            Command::DriverOutputControl {
+                last_gate,
+                scanning,
+            } => {
+                let [lo, hi] = last_gate.to_le_bytes();
+                buf[..3].copy_from_slice(&[lo, hi, scanning]);
+                3
+            }
+            Command::RamYAddress { start, end } => {
+                let [start_lo, start_hi] = start.to_le_bytes();
+                let [end_lo, end_hi] = end.to_le_bytes();
+                *buf = [start_lo, start_hi, end_lo, end_hi];
+                4
+            }
+        };
+
+        &buf[..len]
+    }
+}
+
+/// The controller's status register, read with [crate::Display::read_status].
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Status(pub u8);
+
+impl Status {
+    /// Whether the last HV ready detection found the booster's voltages not ready.
+    pub fn hv_not_ready(self) -> bool {
+        self.0 & 0x20 != 0
+    }
+
+    /// Whether the last VCI detection found the supply voltage below the detection level.
+    pub fn vci_low(self) -> bool {
+        self.0 & 0x10 != 0
+    }
+
+    /// Whether the controller is busy.
+    pub fn busy(self) -> bool {
+        self.0 & 0x04 != 0
+    }
+
+    /// The chip ID, which is 0b01 after power-on.
+    pub fn chip_id(self) -> u8 {
+        self.0 & 0x03
+    }
+}
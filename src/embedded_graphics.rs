@@ -1,43 +1,121 @@
 use embedded_graphics_core::{
-    pixelcolor::BinaryColor,
-    prelude::{Dimensions, DrawTarget},
+    pixelcolor::{BinaryColor, Gray2, GrayColor, PixelColor},
+    prelude::{Dimensions, DrawTarget, Point, PointsIter},
 };
 
 use super::*;
 
-pub struct Framebuffer {
-    framebuffer: [u8; WIDTH * HEIGHT / 8],
+/// How far the image drawn into a framebuffer is rotated clockwise on the panel.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum Rotation {
+    #[default]
+    Deg0,
+    Deg90,
+    Deg180,
+    Deg270,
+}
+
+impl Rotation {
+    /// Maps a point in the rotated image to the point on the panel that shows it.
+    fn to_panel<P: Panel>(self, point: Point) -> Point {
+        let (w, h) = (P::WIDTH as i32, P::HEIGHT as i32);
+        match self {
+            Rotation::Deg0 => point,
+            Rotation::Deg90 => Point::new(w - 1 - point.y, point.x),
+            Rotation::Deg180 => Point::new(w - 1 - point.x, h - 1 - point.y),
+            Rotation::Deg270 => Point::new(point.y, h - 1 - point.x),
+        }
+    }
+
+    /// Returns the bounds of the rotated image.
+    fn bounding_box<P: Panel>(self) -> embedded_graphics_core::primitives::Rectangle {
+        let (width, height) = match self {
+            Rotation::Deg0 | Rotation::Deg180 => (P::WIDTH as u32, P::HEIGHT as u32),
+            Rotation::Deg90 | Rotation::Deg270 => (P::HEIGHT as u32, P::WIDTH as u32),
+        };
+
+        embedded_graphics_core::primitives::Rectangle {
+            top_left: embedded_graphics_core::geometry::Point { x: 0, y: 0 },
+            size: embedded_graphics_core::geometry::Size { width, height },
+        }
+    }
+}
+
+pub struct Framebuffer<P: Panel = Gdeh0154d67> {
+    framebuffer: P::Buffer,
+    dirty: Option<Rect>,
+    rotation: Rotation,
 }
 
 impl Framebuffer {
     pub fn new() -> Self {
+        Self::with_panel(Gdeh0154d67)
+    }
+}
+
+impl<P: Panel> Framebuffer<P> {
+    /// Creates a framebuffer the size of `panel`.
+    pub fn with_panel(_panel: P) -> Self {
         Self {
-            framebuffer: [0xFF; WIDTH * HEIGHT / 8],
+            framebuffer: P::WHITE,
+            dirty: Some(screen_rect::<P>()),
+            rotation: Rotation::Deg0,
         }
     }
 
+    /// Sets the rotation used for drawing from now on. What was already drawn stays in place.
+    pub fn set_rotation(&mut self, rotation: Rotation) {
+        self.rotation = rotation;
+    }
+
+    pub fn rotation(&self) -> Rotation {
+        self.rotation
+    }
+
+    /// Draws the framebuffer to the display. Only the area touched since the last flush is sent
+    /// and refreshed, widened to whole bytes, and nothing happens if nothing was drawn.
     #[cfg_attr(not(feature = "async"), remove_async_await::remove_async_await)]
     pub async fn flush<C: IsDisplayConfiguration>(
         &mut self,
-        display: &mut Display<C>,
+        display: &mut Display<C, P>,
     ) -> Result<(), Error<C>> {
-        display.draw_image(&self.framebuffer, 0, 0, 200, 200).await
+        let Some(dirty) = self.dirty.take() else {
+            return Ok(());
+        };
+
+        match draw_region(display, self.framebuffer.as_ref(), byte_aligned(dirty)).await {
+            Ok(()) => Ok(()),
+            Err(e) => {
+                self.dirty = Some(dirty);
+                Err(e)
+            }
+        }
+    }
+
+    /// Returns the framebuffer's contents, in the layout taken by [Display::draw_image].
+    pub fn buffer(&self) -> &[u8] {
+        self.framebuffer.as_ref()
+    }
+
+    /// Marks the whole framebuffer as changed, so that the next flush sends all of it.
+    pub fn invalidate(&mut self) {
+        self.dirty = Some(screen_rect::<P>());
     }
 }
 
-impl Dimensions for Framebuffer {
+impl<P: Panel + Default> Default for Framebuffer<P> {
+    fn default() -> Self {
+        Self::with_panel(P::default())
+    }
+}
+
+impl<P: Panel> Dimensions for Framebuffer<P> {
     fn bounding_box(&self) -> embedded_graphics_core::primitives::Rectangle {
-        embedded_graphics_core::primitives::Rectangle {
-            top_left: embedded_graphics_core::geometry::Point { x: 0, y: 0 },
-            size: embedded_graphics_core::geometry::Size {
-                width: WIDTH as u32,
-                height: HEIGHT as u32,
-            },
-        }
+        self.rotation.bounding_box::<P>()
     }
 }
 
-impl DrawTarget for Framebuffer {
+impl<P: Panel> DrawTarget for Framebuffer<P> {
     type Color = BinaryColor;
 
     type Error = ();
@@ -47,23 +125,819 @@ impl DrawTarget for Framebuffer {
         I: IntoIterator<Item = embedded_graphics_core::Pixel<Self::Color>>,
     {
         for embedded_graphics_core::Pixel(point, color) in pixels {
-            if point.x < 0 || point.x >= WIDTH as i32 || point.y < 0 || point.y >= HEIGHT as i32 {
+            let point = self.rotation.to_panel::<P>(point);
+            let Some((byte_index, bit_index)) = pixel_index::<P>(point) else {
                 continue;
-            }
-            let x = point.x as usize;
-            let y = point.y as usize;
-            let byte_index = x / 8 + y * WIDTH / 8;
-            let byte = &mut self.framebuffer[byte_index];
-            let bit_index = 7 - x % 8;
-
+            };
+            let byte = &mut self.framebuffer.as_mut()[byte_index];
+            let pixel = Rect {
+                x: Span {
+                    lo: point.x as i16,
+                    hi: point.x as i16 + 1,
+                },
+                y: Span {
+                    lo: point.y as i16,
+                    hi: point.y as i16 + 1,
+                },
+            };
             match color {
                 //White Pixel
                 BinaryColor::On => *byte |= 0b1 << bit_index,
                 //Black Pixel
                 BinaryColor::Off => *byte &= !(0b1 << bit_index),
             }
+            self.mark_dirty(pixel);
+        }
+
+        Ok(())
+    }
+
+    fn fill_contiguous<I>(
+        &mut self,
+        area: &embedded_graphics_core::primitives::Rectangle,
+        colors: I,
+    ) -> Result<(), Self::Error>
+    where
+        I: IntoIterator<Item = Self::Color>,
+    {
+        let visible = area.intersection(&self.bounding_box());
+        let Some(rect) = self.panel_area(&visible) else {
+            return Ok(());
+        };
+        self.mark_dirty(rect);
+
+        // Rows of the image run along the panel's rows in these rotations, so they can be filled
+        // a byte at a time. Otherwise they run down its columns, and each pixel is set on its own.
+        let step = match self.rotation {
+            Rotation::Deg0 => 1,
+            Rotation::Deg180 => -1,
+            Rotation::Deg90 | Rotation::Deg270 => {
+                // The area has been clipped to the screen already, so the pixels can be set
+                // directly
+                let buffer = self.framebuffer.as_mut();
+                for (point, color) in area.points().zip(colors) {
+                    if !visible.contains(point) {
+                        continue;
+                    }
+                    let point = self.rotation.to_panel::<P>(point);
+                    let (x, y) = (point.x as usize, point.y as usize);
+                    let byte = &mut buffer[y * stride::<P>() + x / 8];
+                    match color {
+                        BinaryColor::On => *byte |= 0x80 >> (x % 8),
+                        BinaryColor::Off => *byte &= !(0x80 >> (x % 8)),
+                    }
+                }
+                return Ok(());
+            }
+        };
+
+        let width = area.size.width as usize;
+        let (left, top) = (visible.top_left - area.top_left).into();
+        let (left, top) = (left as usize, top as usize);
+        let right = width - left - visible.size.width as usize;
+        let mut colors = colors.into_iter();
+        // Skips `n` colors, returning false if they ran out
+        let skip = |colors: &mut I::IntoIter, n: usize| n == 0 || colors.nth(n - 1).is_some();
+
+        if !skip(&mut colors, top * width) {
+            return Ok(());
+        }
+        for y in 0..visible.size.height as i32 {
+            if !skip(&mut colors, left) {
+                break;
+            }
+            let start = self
+                .rotation
+                .to_panel::<P>(visible.top_left + Point::new(0, y));
+            let row = &mut self.framebuffer.as_mut()[start.y as usize * stride::<P>()..];
+            let row_colors = colors.by_ref().take(visible.size.width as usize);
+            set_row(row, start.x, step, row_colors);
+            if !skip(&mut colors, right) {
+                break;
+            }
+        }
+
+        Ok(())
+    }
+
+    fn fill_solid(
+        &mut self,
+        area: &embedded_graphics_core::primitives::Rectangle,
+        color: Self::Color,
+    ) -> Result<(), Self::Error> {
+        let visible = area.intersection(&self.bounding_box());
+        let Some(rect) = self.panel_area(&visible) else {
+            return Ok(());
+        };
+        self.mark_dirty(rect);
+
+        let value = match color {
+            BinaryColor::On => 0xFF,
+            BinaryColor::Off => 0x00,
+        };
+        let (lo, hi) = (rect.x.lo as usize, rect.x.hi as usize);
+        let (first, last) = (lo / 8, (hi - 1) / 8);
+        let head = 0xFFu8 >> (lo % 8);
+        let tail = (0xFF00u16 >> ((hi - 1) % 8 + 1)) as u8;
+        let set = |byte: &mut u8, mask: u8| *byte = (*byte & !mask) | (value & mask);
+
+        for y in rect.y.lo..rect.y.hi {
+            let start = y as usize * stride::<P>();
+            let row = &mut self.framebuffer.as_mut()[start..start + stride::<P>()];
+            if first == last {
+                set(&mut row[first], head & tail);
+                continue;
+            }
+            set(&mut row[first], head);
+            row[first + 1..last].fill(value);
+            set(&mut row[last], tail);
+        }
+
+        Ok(())
+    }
+
+    fn clear(&mut self, color: Self::Color) -> Result<(), Self::Error> {
+        let value = match color {
+            BinaryColor::On => 0xFF,
+            BinaryColor::Off => 0x00,
+        };
+        self.framebuffer.as_mut().fill(value);
+        self.dirty = Some(screen_rect::<P>());
+
+        Ok(())
+    }
+}
+
+/// Sets the pixels of `row` from `colors`, starting at `x` and moving `step` pixels at a time,
+/// collecting the pixels of each byte before writing it.
+fn set_row(row: &mut [u8], mut x: i32, step: i32, colors: impl Iterator<Item = BinaryColor>) {
+    let (mut index, mut mask, mut bits) = (x as usize / 8, 0u8, 0u8);
+    for color in colors {
+        if x as usize / 8 != index {
+            row[index] = (row[index] & !mask) | bits;
+            (index, mask, bits) = (x as usize / 8, 0, 0);
+        }
+        let bit = 0x80 >> (x % 8);
+        mask |= bit;
+        if color == BinaryColor::On {
+            bits |= bit;
+        }
+        x += step;
+    }
+    row[index] = (row[index] & !mask) | bits;
+}
+
+impl<P: Panel> Framebuffer<P> {
+    fn mark_dirty(&mut self, rect: Rect) {
+        self.dirty = Some(match self.dirty {
+            Some(dirty) => dirty.union(rect),
+            None => rect,
+        });
+    }
+
+    /// Finds the area of the panel covered by `area` of the rotated image, which must be on the
+    /// screen. Returns `None` if it is empty.
+    fn panel_area(&self, area: &embedded_graphics_core::primitives::Rectangle) -> Option<Rect> {
+        let a = self.rotation.to_panel::<P>(area.top_left);
+        let b = self.rotation.to_panel::<P>(area.bottom_right()?);
+
+        Some(Rect {
+            x: Span {
+                lo: a.x.min(b.x) as i16,
+                hi: a.x.max(b.x) as i16 + 1,
+            },
+            y: Span {
+                lo: a.y.min(b.y) as i16,
+                hi: a.y.max(b.y) as i16 + 1,
+            },
+        })
+    }
+}
+
+/// A [Framebuffer] that keeps a copy of what it last sent to the display.
+///
+/// Flushing compares the two and only sends the area that actually changed, skipping the refresh
+/// entirely when redrawing produced the same pixels. This costs another framebuffer's worth of RAM.
+pub struct ShadowFramebuffer<P: Panel = Gdeh0154d67> {
+    framebuffer: Framebuffer<P>,
+    shadow: P::Buffer,
+    synced: bool,
+}
+
+impl ShadowFramebuffer {
+    pub fn new() -> Self {
+        Self::with_panel(Gdeh0154d67)
+    }
+}
+
+impl<P: Panel> ShadowFramebuffer<P> {
+    /// Creates a framebuffer the size of `panel`.
+    pub fn with_panel(panel: P) -> Self {
+        Self {
+            framebuffer: Framebuffer::with_panel(panel),
+            shadow: P::WHITE,
+            synced: false,
         }
+    }
+
+    /// Draws the pixels that changed since the last flush to the display.
+    #[cfg_attr(not(feature = "async"), remove_async_await::remove_async_await)]
+    pub async fn flush<C: IsDisplayConfiguration>(
+        &mut self,
+        display: &mut Display<C, P>,
+    ) -> Result<(), Error<C>> {
+        let Some(dirty) = self.framebuffer.dirty.take() else {
+            return Ok(());
+        };
+        let dirty = byte_aligned(dirty);
+        let changed = if self.synced {
+            self.changed_rect(dirty)
+        } else {
+            Some(dirty)
+        };
+        let Some(changed) = changed else {
+            return Ok(());
+        };
+
+        if let Err(e) = draw_region(display, self.framebuffer.buffer(), changed).await {
+            self.framebuffer.dirty = Some(dirty);
+            return Err(e);
+        }
+
+        for y in changed.y.lo..changed.y.hi {
+            let start = y as usize * stride::<P>() + changed.x.lo as usize / 8;
+            let end = start + changed.x.size() as usize / 8;
+            self.shadow.as_mut()[start..end].copy_from_slice(&self.framebuffer.buffer()[start..end]);
+        }
+        self.synced = true;
 
         Ok(())
     }
+
+    /// See [Framebuffer::set_rotation].
+    pub fn set_rotation(&mut self, rotation: Rotation) {
+        self.framebuffer.set_rotation(rotation);
+    }
+
+    pub fn rotation(&self) -> Rotation {
+        self.framebuffer.rotation()
+    }
+
+    /// Forgets what is on the display, so that the next flush sends the whole framebuffer.
+    pub fn invalidate(&mut self) {
+        self.framebuffer.invalidate();
+        self.synced = false;
+    }
+
+    /// Finds the byte-aligned bounding box of the bytes in `rect` that differ from the shadow.
+    fn changed_rect(&self, rect: Rect) -> Option<Rect> {
+        let (framebuffer, shadow) = (self.framebuffer.buffer(), self.shadow.as_ref());
+        let mut changed: Option<Rect> = None;
+        for y in rect.y.lo..rect.y.hi {
+            for x in (rect.x.lo..rect.x.hi).step_by(8) {
+                let i = y as usize * stride::<P>() + x as usize / 8;
+                if framebuffer[i] ^ shadow[i] == 0 {
+                    continue;
+                }
+                let byte = Rect {
+                    x: Span { lo: x, hi: x + 8 },
+                    y: Span { lo: y, hi: y + 1 },
+                };
+                changed = Some(match changed {
+                    Some(changed) => changed.union(byte),
+                    None => byte,
+                });
+            }
+        }
+
+        changed
+    }
+}
+
+impl<P: Panel + Default> Default for ShadowFramebuffer<P> {
+    fn default() -> Self {
+        Self::with_panel(P::default())
+    }
+}
+
+impl<P: Panel> Dimensions for ShadowFramebuffer<P> {
+    fn bounding_box(&self) -> embedded_graphics_core::primitives::Rectangle {
+        self.framebuffer.bounding_box()
+    }
+}
+
+impl<P: Panel> DrawTarget for ShadowFramebuffer<P> {
+    type Color = BinaryColor;
+
+    type Error = ();
+
+    fn draw_iter<I>(&mut self, pixels: I) -> Result<(), Self::Error>
+    where
+        I: IntoIterator<Item = embedded_graphics_core::Pixel<Self::Color>>,
+    {
+        self.framebuffer.draw_iter(pixels)
+    }
+
+    fn fill_contiguous<I>(
+        &mut self,
+        area: &embedded_graphics_core::primitives::Rectangle,
+        colors: I,
+    ) -> Result<(), Self::Error>
+    where
+        I: IntoIterator<Item = Self::Color>,
+    {
+        self.framebuffer.fill_contiguous(area, colors)
+    }
+
+    fn fill_solid(
+        &mut self,
+        area: &embedded_graphics_core::primitives::Rectangle,
+        color: Self::Color,
+    ) -> Result<(), Self::Error> {
+        self.framebuffer.fill_solid(area, color)
+    }
+
+    fn clear(&mut self, color: Self::Color) -> Result<(), Self::Error> {
+        self.framebuffer.clear(color)
+    }
+}
+
+/// A framebuffer for four-level grayscale images, drawn with [Display::draw_image_gray2].
+///
+/// Black and white framebuffers flushed to the same display need [Framebuffer::invalidate]
+/// afterwards, see [Display::draw_image_gray2].
+pub struct Gray2Framebuffer<P: Panel = Gdeh0154d67> {
+    low: P::Buffer,
+    high: P::Buffer,
+    rotation: Rotation,
+}
+
+impl Gray2Framebuffer {
+    pub fn new() -> Self {
+        Self::with_panel(Gdeh0154d67)
+    }
+}
+
+impl<P: Panel> Gray2Framebuffer<P> {
+    /// Creates a framebuffer the size of `panel`.
+    pub fn with_panel(_panel: P) -> Self {
+        Self {
+            low: P::WHITE,
+            high: P::WHITE,
+            rotation: Rotation::Deg0,
+        }
+    }
+
+    /// See [Framebuffer::set_rotation].
+    pub fn set_rotation(&mut self, rotation: Rotation) {
+        self.rotation = rotation;
+    }
+
+    pub fn rotation(&self) -> Rotation {
+        self.rotation
+    }
+
+    #[cfg_attr(not(feature = "async"), remove_async_await::remove_async_await)]
+    pub async fn flush<C: IsDisplayConfiguration>(
+        &mut self,
+        display: &mut Display<C, P>,
+    ) -> Result<(), Error<C>> {
+        let screen = screen_rect::<P>();
+        display
+            .draw_image_gray2(
+                self.low.as_ref(),
+                self.high.as_ref(),
+                screen.x.lo,
+                screen.y.lo,
+                ceil_multiple(screen.x.hi, 8),
+                screen.y.hi,
+            )
+            .await
+    }
+}
+
+impl<P: Panel + Default> Default for Gray2Framebuffer<P> {
+    fn default() -> Self {
+        Self::with_panel(P::default())
+    }
+}
+
+impl<P: Panel> Dimensions for Gray2Framebuffer<P> {
+    fn bounding_box(&self) -> embedded_graphics_core::primitives::Rectangle {
+        self.rotation.bounding_box::<P>()
+    }
+}
+
+impl<P: Panel> DrawTarget for Gray2Framebuffer<P> {
+    type Color = Gray2;
+
+    type Error = ();
+
+    fn draw_iter<I>(&mut self, pixels: I) -> Result<(), Self::Error>
+    where
+        I: IntoIterator<Item = embedded_graphics_core::Pixel<Self::Color>>,
+    {
+        for embedded_graphics_core::Pixel(point, color) in pixels {
+            let Some((byte_index, bit_index)) =
+                pixel_index::<P>(self.rotation.to_panel::<P>(point))
+            else {
+                continue;
+            };
+            let luma = color.luma();
+
+            for (plane, bit) in [(&mut self.low, luma & 0b01), (&mut self.high, luma & 0b10)] {
+                let byte = &mut plane.as_mut()[byte_index];
+                if bit != 0 {
+                    *byte |= 0b1 << bit_index;
+                } else {
+                    *byte &= !(0b1 << bit_index);
+                }
+            }
+        }
+
+        Ok(())
+    }
+}
+
+/// The colors of a black, white and red panel.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Hash)]
+pub enum TriColor {
+    #[default]
+    White,
+    Black,
+    Red,
+}
+
+impl PixelColor for TriColor {
+    type Raw = ();
+}
+
+impl From<BinaryColor> for TriColor {
+    fn from(color: BinaryColor) -> Self {
+        match color {
+            BinaryColor::On => TriColor::White,
+            BinaryColor::Off => TriColor::Black,
+        }
+    }
+}
+
+/// A framebuffer for black, white and red panels, drawn with [Display::draw_image_bwr].
+pub struct TriColorFramebuffer<P: Panel = Gdeh0154z90> {
+    black: P::Buffer,
+    red: P::Buffer,
+    rotation: Rotation,
+}
+
+impl TriColorFramebuffer {
+    pub fn new() -> Self {
+        Self::with_panel(Gdeh0154z90)
+    }
+}
+
+impl<P: Panel> TriColorFramebuffer<P> {
+    /// Creates a framebuffer the size of `panel`.
+    pub fn with_panel(_panel: P) -> Self {
+        Self {
+            black: P::WHITE,
+            red: P::WHITE,
+            rotation: Rotation::Deg0,
+        }
+    }
+
+    /// See [Framebuffer::set_rotation].
+    pub fn set_rotation(&mut self, rotation: Rotation) {
+        self.rotation = rotation;
+    }
+
+    pub fn rotation(&self) -> Rotation {
+        self.rotation
+    }
+
+    /// Returns the black plane, in the layout taken by [Display::draw_image_bwr].
+    pub fn black(&self) -> &[u8] {
+        self.black.as_ref()
+    }
+
+    /// Returns the red plane, in the layout taken by [Display::draw_image_bwr].
+    pub fn red(&self) -> &[u8] {
+        self.red.as_ref()
+    }
+
+    /// Draws the framebuffer to the display with a full refresh.
+    #[cfg_attr(not(feature = "async"), remove_async_await::remove_async_await)]
+    pub async fn flush<C: IsDisplayConfiguration>(
+        &mut self,
+        display: &mut Display<C, P>,
+    ) -> Result<(), Error<C>> {
+        let screen = screen_rect::<P>();
+        display
+            .draw_image_bwr(
+                self.black.as_ref(),
+                self.red.as_ref(),
+                screen.x.lo,
+                screen.y.lo,
+                ceil_multiple(screen.x.hi, 8),
+                screen.y.hi,
+            )
+            .await
+    }
+}
+
+impl<P: Panel + Default> Default for TriColorFramebuffer<P> {
+    fn default() -> Self {
+        Self::with_panel(P::default())
+    }
+}
+
+impl<P: Panel> Dimensions for TriColorFramebuffer<P> {
+    fn bounding_box(&self) -> embedded_graphics_core::primitives::Rectangle {
+        self.rotation.bounding_box::<P>()
+    }
+}
+
+impl<P: Panel> DrawTarget for TriColorFramebuffer<P> {
+    type Color = TriColor;
+
+    type Error = ();
+
+    fn draw_iter<I>(&mut self, pixels: I) -> Result<(), Self::Error>
+    where
+        I: IntoIterator<Item = embedded_graphics_core::Pixel<Self::Color>>,
+    {
+        for embedded_graphics_core::Pixel(point, color) in pixels {
+            let Some((byte_index, bit_index)) =
+                pixel_index::<P>(self.rotation.to_panel::<P>(point))
+            else {
+                continue;
+            };
+
+            let planes = [
+                (&mut self.black, color == TriColor::Black),
+                (&mut self.red, color == TriColor::Red),
+            ];
+            for (plane, set) in planes {
+                let byte = &mut plane.as_mut()[byte_index];
+                if set {
+                    *byte &= !(0b1 << bit_index);
+                } else {
+                    *byte |= 0b1 << bit_index;
+                }
+            }
+        }
+
+        Ok(())
+    }
+}
+
+impl<C: IsDisplayConfiguration, P: Panel> Display<C, P> {
+    /// Starts drawing to a small area of the screen without a full framebuffer. The area is
+    /// widened to whole bytes horizontally, and `buffer` must hold
+    /// `(x_hi - x_lo + 7) / 8 * (y_hi - y_lo)` bytes for it, counting from the widened edges.
+    ///
+    /// The [Window] is drawn to with embedded-graphics in screen coordinates, ignoring anything
+    /// outside its area, and starts out white. It is sent to the display and refreshed by
+    /// [Window::commit], or when it is dropped in blocking mode.
+    pub fn window<'a>(
+        &'a mut self,
+        buffer: &'a mut [u8],
+        x_lo: i16,
+        y_lo: i16,
+        x_hi: i16,
+        y_hi: i16,
+    ) -> Result<Window<'a, C, P>, Error<C>> {
+        let rect = byte_aligned(Rect {
+            x: Span { lo: x_lo, hi: x_hi },
+            y: Span { lo: y_lo, hi: y_hi },
+        });
+        let len = rect.x.size().max(0) as usize / 8 * rect.y.size().max(0) as usize;
+        let Some(buffer) = buffer.get_mut(..len) else {
+            return Err(DisplayError::InvalidBitmap);
+        };
+        buffer.fill(0xFF);
+
+        Ok(Window {
+            display: self,
+            buffer,
+            rect,
+            dirty: false,
+        })
+    }
+}
+
+/// A byte-aligned area of the screen that is drawn to directly, made with [Display::window].
+pub struct Window<'a, C: IsDisplayConfiguration, P: Panel = Gdeh0154d67> {
+    display: &'a mut Display<C, P>,
+    buffer: &'a mut [u8],
+    rect: Rect,
+    dirty: bool,
+}
+
+impl<C: IsDisplayConfiguration, P: Panel> Window<'_, C, P> {
+    /// Draws the window to the display. Nothing is sent if nothing was drawn.
+    #[cfg_attr(not(feature = "async"), remove_async_await::remove_async_await)]
+    pub async fn commit(mut self) -> Result<(), Error<C>> {
+        self.flush().await
+    }
+
+    #[cfg_attr(not(feature = "async"), remove_async_await::remove_async_await)]
+    async fn flush(&mut self) -> Result<(), Error<C>> {
+        if !self.dirty {
+            return Ok(());
+        }
+        self.dirty = false;
+
+        let rect = self.rect;
+        self.display
+            .draw_image(self.buffer, rect.x.lo, rect.y.lo, rect.x.hi, rect.y.hi)
+            .await
+    }
+}
+
+/// Commits the window, ignoring errors. Use [Window::commit] to see them.
+#[cfg(not(feature = "async"))]
+impl<C: IsDisplayConfiguration, P: Panel> Drop for Window<'_, C, P> {
+    fn drop(&mut self) {
+        let _ = self.flush();
+    }
+}
+
+impl<C: IsDisplayConfiguration, P: Panel> Dimensions for Window<'_, C, P> {
+    fn bounding_box(&self) -> embedded_graphics_core::primitives::Rectangle {
+        area_bounding_box(self.rect)
+    }
+}
+
+impl<C: IsDisplayConfiguration, P: Panel> DrawTarget for Window<'_, C, P> {
+    type Color = BinaryColor;
+
+    type Error = ();
+
+    fn draw_iter<I>(&mut self, pixels: I) -> Result<(), Self::Error>
+    where
+        I: IntoIterator<Item = embedded_graphics_core::Pixel<Self::Color>>,
+    {
+        if draw_area(self.buffer, self.rect, pixels) {
+            self.dirty = true;
+        }
+
+        Ok(())
+    }
+}
+
+impl<C: IsDisplayConfiguration, P: Panel> Display<C, P> {
+    /// Draws the whole screen a band of rows at a time, for when there is not enough RAM for a
+    /// [Framebuffer]. `buffer` holds one band, which is as many full rows as fit in it.
+    ///
+    /// `draw` is called once for each band with a [Band] to draw the whole picture into, and only
+    /// the pixels inside the band are kept, so `draw` must draw the same picture every time. Once
+    /// every band has been sent, the screen is refreshed.
+    #[cfg_attr(not(feature = "async"), remove_async_await::remove_async_await)]
+    pub async fn draw_banded<F>(&mut self, buffer: &mut [u8], mut draw: F) -> Result<(), Error<C>>
+    where
+        F: FnMut(&mut Band<'_>),
+    {
+        let rows = (buffer.len() / stride::<P>()).min(P::HEIGHT as usize) as i16;
+        if rows == 0 {
+            return Err(DisplayError::InvalidBitmap);
+        }
+
+        let screen = screen_rect::<P>();
+        for y_lo in (screen.y.lo..screen.y.hi).step_by(rows as usize) {
+            let rect = Rect {
+                x: Span {
+                    lo: screen.x.lo,
+                    hi: ceil_multiple(screen.x.hi, 8),
+                },
+                y: Span {
+                    lo: y_lo,
+                    hi: (y_lo + rows).min(screen.y.hi),
+                },
+            };
+            let band = &mut buffer[..stride::<P>() * rect.y.size() as usize];
+            band.fill(0xFF);
+            draw(&mut Band {
+                buffer: band,
+                rect,
+                screen,
+            });
+
+            self.write_image_inner(Ram::BlackWhite, band, stride::<P>(), rect, 0x00)
+                .await?;
+        }
+        self.refresh(screen).await?;
+
+        Ok(())
+    }
+}
+
+/// A band of full rows of the screen, drawn to by the closure passed to [Display::draw_banded].
+///
+/// Its bounding box is the whole screen, so that the picture is laid out the same way for every
+/// band, and pixels outside the band are ignored.
+pub struct Band<'a> {
+    buffer: &'a mut [u8],
+    rect: Rect,
+    screen: Rect,
+}
+
+impl Dimensions for Band<'_> {
+    fn bounding_box(&self) -> embedded_graphics_core::primitives::Rectangle {
+        area_bounding_box(self.screen)
+    }
+}
+
+impl DrawTarget for Band<'_> {
+    type Color = BinaryColor;
+
+    type Error = ();
+
+    fn draw_iter<I>(&mut self, pixels: I) -> Result<(), Self::Error>
+    where
+        I: IntoIterator<Item = embedded_graphics_core::Pixel<Self::Color>>,
+    {
+        draw_area(self.buffer, self.rect, pixels);
+
+        Ok(())
+    }
+}
+
+/// Draws the part of the full-screen `framebuffer` inside `rect`.
+#[cfg_attr(not(feature = "async"), remove_async_await::remove_async_await)]
+async fn draw_region<C: IsDisplayConfiguration, P: Panel>(
+    display: &mut Display<C, P>,
+    framebuffer: &[u8],
+    rect: Rect,
+) -> Result<(), Error<C>> {
+    let start = rect.y.lo as usize * stride::<P>() + rect.x.lo as usize / 8;
+    display
+        .draw_image_strided(
+            &framebuffer[start..],
+            stride::<P>(),
+            rect.x.lo,
+            rect.y.lo,
+            rect.x.hi,
+            rect.y.hi,
+        )
+        .await
+}
+
+/// Draws `pixels` into `buffer`, which holds the byte-aligned `rect` of the screen, skipping
+/// the ones outside it. Returns whether any pixel was drawn.
+fn draw_area<I>(buffer: &mut [u8], rect: Rect, pixels: I) -> bool
+where
+    I: IntoIterator<Item = embedded_graphics_core::Pixel<BinaryColor>>,
+{
+    let stride = rect.x.size() as usize / 8;
+    let mut drawn = false;
+    for embedded_graphics_core::Pixel(point, color) in pixels {
+        let x = point.x - rect.x.lo as i32;
+        let y = point.y - rect.y.lo as i32;
+        if x < 0 || x >= rect.x.size() as i32 || y < 0 || y >= rect.y.size() as i32 {
+            continue;
+        }
+        let (x, y) = (x as usize, y as usize);
+        let byte = &mut buffer[y * stride + x / 8];
+        let bit_index = 7 - x % 8;
+        drawn = true;
+
+        match color {
+            BinaryColor::On => *byte |= 0b1 << bit_index,
+            BinaryColor::Off => *byte &= !(0b1 << bit_index),
+        }
+    }
+
+    drawn
+}
+
+/// Returns `rect` as an embedded-graphics rectangle.
+fn area_bounding_box(rect: Rect) -> embedded_graphics_core::primitives::Rectangle {
+    embedded_graphics_core::primitives::Rectangle {
+        top_left: Point::new(rect.x.lo as i32, rect.y.lo as i32),
+        size: embedded_graphics_core::geometry::Size {
+            width: rect.x.size().max(0) as u32,
+            height: rect.y.size().max(0) as u32,
+        },
+    }
+}
+
+/// Widens `rect` horizontally to whole bytes.
+fn byte_aligned(rect: Rect) -> Rect {
+    Rect {
+        x: Span {
+            lo: floor_multiple(rect.x.lo, 8),
+            hi: ceil_multiple(rect.x.hi, 8),
+        },
+        y: rect.y,
+    }
+}
+
+/// Finds the byte and bit holding `point` in a full-screen bitmap, if it is on the screen.
+fn pixel_index<P: Panel>(point: Point) -> Option<(usize, usize)> {
+    if point.x < 0 || point.x >= P::WIDTH as i32 || point.y < 0 || point.y >= P::HEIGHT as i32 {
+        return None;
+    }
+    let x = point.x as usize;
+    let y = point.y as usize;
+
+    Some((x / 8 + y * stride::<P>(), 7 - x % 8))
 }
@@ -22,6 +22,15 @@ impl Span {
             None
         }
     }
+
+    /// Computes the smallest span containing both spans.
+    #[cfg(feature = "embedded-graphics")]
+    pub(crate) fn union(self, other: Span) -> Span {
+        Span {
+            lo: self.lo.min(other.lo),
+            hi: self.hi.max(other.hi),
+        }
+    }
 }
 
 #[derive(Clone, Copy, Debug)]
@@ -39,4 +48,13 @@ impl Rect {
 
         Some(Rect { x, y })
     }
+
+    /// Computes the smallest rectangle containing both rectangles.
+    #[cfg(feature = "embedded-graphics")]
+    pub(crate) fn union(self, other: Rect) -> Rect {
+        Rect {
+            x: self.x.union(other.x),
+            y: self.y.union(other.y),
+        }
+    }
 }
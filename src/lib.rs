@@ -1,8 +1,13 @@
 #![no_std]
 
-use core::fmt::Debug;
+#[cfg(feature = "std")]
+extern crate std;
 
-use embedded_hal::digital::OutputPin;
+#[cfg(feature = "async")]
+use core::{future::Future, pin::pin, task::Poll};
+use core::{fmt::Debug, marker::PhantomData};
+
+use embedded_hal::{digital::OutputPin, spi::Operation};
 #[cfg(not(feature = "async"))]
 use embedded_hal::{
     delay::DelayNs,
@@ -21,30 +26,49 @@ pub use embedded_graphics::*;
 
 #[cfg(feature = "embedded-graphics")]
 mod embedded_graphics;
+pub mod command;
 mod geometry;
+mod panel;
+#[cfg(feature = "std")]
+pub mod record;
+#[cfg(feature = "sim")]
+pub mod sim;
+#[cfg(feature = "std")]
+pub mod snapshot;
+mod temperature;
+mod waveform;
 mod private {
     pub trait Internal {}
 }
 
+use command::*;
 use geometry::*;
+pub use panel::*;
 use private::*;
+pub use temperature::*;
+pub use waveform::*;
 
-const WIDTH: usize = 200;
-const HEIGHT: usize = 200;
-const SCREEN_RECT: Rect = Rect {
-    x: Span {
-        lo: 0,
-        hi: WIDTH as i16,
-    },
-    y: Span {
-        lo: 0,
-        hi: HEIGHT as i16,
-    },
-};
+/// The largest number of bytes [Display::set_fill_chunk] allows per SPI transaction.
+pub const MAX_FILL_CHUNK: usize = 256;
+
+/// The size of the buffer that chunks are written from when filling the RAM.
+const FILL_BUFFER: usize = 32;
+
+/// The display update control 2 (0x22) values for full and partial refreshes with the waveform
+/// written to the LUT register, rather than one loaded from OTP.
+const UPDATE_FULL_LUT: u8 = 0xc4;
+const UPDATE_PARTIAL_LUT: u8 = 0xcc;
+/// The display update control 2 (0x22) value that turns the clock on, loads the temperature and
+/// turns the clock off again.
+const LOAD_TEMPERATURE: u8 = 0xa1;
 
 #[derive(Debug)]
 pub enum DisplayError<Spi, Input, Output> {
     BusyTimeout,
+    /// The bitmap is too short for the area it is drawn to, or its stride is too small.
+    InvalidBitmap,
+    /// The panel does not support the operation, such as drawing red on a black and white panel.
+    Unsupported,
     Spi(Spi),
     Input(Input),
     Output(Output),
@@ -62,6 +86,15 @@ type Error<C> = DisplayError<
     <C as IsDisplayConfiguration>::OutputError,
 >;
 
+type Config<C> = DisplayConfiguration<
+    <C as IsDisplayConfiguration>::Spi,
+    <C as IsDisplayConfiguration>::Dc,
+    <C as IsDisplayConfiguration>::Rst,
+    <C as IsDisplayConfiguration>::Busy,
+    <C as IsDisplayConfiguration>::Delay,
+    <C as IsDisplayConfiguration>::Wait,
+>;
+
 /// A helper trait to avoid repeating type constraints. See [DisplayConfiguration].
 pub trait IsDisplayConfiguration: Internal {
     type Spi: SpiDevice<Error = Self::SpiError>;
@@ -71,41 +104,29 @@ pub trait IsDisplayConfiguration: Internal {
     type Delay: DelayNs + Clone;
     #[cfg(not(feature = "async"))]
     type Wait: BusyWait;
+    #[cfg(feature = "async")]
+    type Wait: BusyWait<Self::Busy>;
 
     type SpiError: spi::Error;
     type OutputError: Debug;
     type InputError: Debug;
 
-    #[cfg(not(feature = "async"))]
-    fn get(
-        self,
-    ) -> DisplayConfiguration<Self::Spi, Self::Dc, Self::Rst, Self::Busy, Self::Delay, Self::Wait>;
-    #[cfg(feature = "async")]
-    fn get(
-        self,
-    ) -> DisplayConfiguration<Self::Spi, Self::Dc, Self::Rst, Self::Busy, Self::Delay>;
+    fn get(self) -> Config<Self>;
 }
 
-pub struct DisplayConfiguration<Spi, Dc, Rst, Busy, Delay, #[cfg(not(feature = "async"))] Wait> {
+pub struct DisplayConfiguration<Spi, Dc, Rst, Busy, Delay, Wait> {
     pub spi: Spi,
     pub dc: Dc,
     pub rst: Rst,
     pub busy: Busy,
     pub delay: Delay,
-    #[cfg(not(feature = "async"))]
     pub busy_wait: Wait,
 }
 
-#[cfg(not(feature = "async"))]
 impl<Spi, Dc, Rst, Busy, Delay, BusyCallback> Internal
     for DisplayConfiguration<Spi, Dc, Rst, Busy, Delay, BusyCallback>
 {
 }
-#[cfg(feature = "async")]
-impl<Spi, Dc, Rst, Busy, Delay> Internal
-    for DisplayConfiguration<Spi, Dc, Rst, Busy, Delay>
-{
-}
 
 #[cfg(not(feature = "async"))]
 impl<Spi, Dc, Rst, Busy, Delay, Wait, SpiError, OutputError, InputError> IsDisplayConfiguration
@@ -131,22 +152,20 @@ where
     type OutputError = OutputError;
     type InputError = InputError;
 
-    fn get(
-        self,
-    ) -> DisplayConfiguration<Self::Spi, Self::Dc, Self::Rst, Self::Busy, Self::Delay, Self::Wait>
-    {
+    fn get(self) -> Config<Self> {
         self
     }
 }
 #[cfg(feature = "async")]
-impl<Spi, Dc, Rst, Busy, Delay, SpiError, OutputError, InputError> IsDisplayConfiguration
-    for DisplayConfiguration<Spi, Dc, Rst, Busy, Delay>
+impl<Spi, Dc, Rst, Busy, Delay, Wait, SpiError, OutputError, InputError> IsDisplayConfiguration
+    for DisplayConfiguration<Spi, Dc, Rst, Busy, Delay, Wait>
 where
     Spi: SpiDevice<Error = SpiError>,
     Dc: OutputPin<Error = OutputError>,
     Rst: OutputPin<Error = OutputError>,
     Busy: InputPin<Error = InputError>,
     Delay: DelayNs + Clone,
+    Wait: BusyWait<Busy>,
     SpiError: spi::Error,
     OutputError: Debug,
     InputError: Debug,
@@ -156,14 +175,12 @@ where
     type Rst = Rst;
     type Busy = Busy;
     type Delay = Delay;
+    type Wait = Wait;
     type SpiError = SpiError;
     type OutputError = OutputError;
     type InputError = InputError;
 
-    fn get(
-        self,
-    ) -> DisplayConfiguration<Self::Spi, Self::Dc, Self::Rst, Self::Busy, Self::Delay>
-    {
+    fn get(self) -> Config<Self> {
         self
     }
 }
@@ -173,17 +190,34 @@ pub struct BusyTimeout;
 
 #[cfg(not(feature = "async"))]
 pub trait BusyWait {
+    /// Called before each wait for the busy pin, so that a timeout can start counting again.
+    fn start_wait(&mut self) {}
+
     fn poll_wait(&mut self) -> Result<(), BusyTimeout>;
 }
 
-#[cfg(not(feature = "async"))]
+/// How the display waits for the busy pin to go low in async mode.
+#[cfg(feature = "async")]
+#[allow(async_fn_in_trait)]
+pub trait BusyWait<Busy: InputPin> {
+    /// Waits until `busy` is low. Returns `Ok(Err(BusyTimeout))` if the timeout runs out first.
+    async fn wait(&mut self, busy: &mut Busy) -> Result<Result<(), BusyTimeout>, Busy::Error>;
+}
+
+/// Waits for the busy pin with a timeout, which is 0 to wait forever. The timeout applies to each
+/// wait on its own.
+///
+/// In blocking mode, this checks the pin every `delay_ms`. In async mode, it waits for the pin's
+/// edge and the timeout at the same time, or after `polling()` reads the pin's level every
+/// `delay_ms` instead.
 pub struct DelayWaiter<Delay> {
     delay: Delay,
     delay_ms: u32,
     timeout_ms: u32,
+    #[cfg(not(feature = "async"))]
+    elapsed_ms: u32,
 }
 
-#[cfg(not(feature = "async"))]
 impl<Delay> DelayWaiter<Delay>
 where Delay: DelayNs {
     pub fn new(delay: Delay) -> Self {
@@ -191,6 +225,8 @@ where Delay: DelayNs {
             delay,
             delay_ms: 1,
             timeout_ms: 100_000,
+            #[cfg(not(feature = "async"))]
+            elapsed_ms: 0,
         }
     }
 
@@ -207,63 +243,479 @@ where Delay: DelayNs {
             ..self
         }
     }
+
+    /// Reads the busy pin's level every `delay_ms` instead of waiting for its edge, for pins whose
+    /// interrupts are unreliable.
+    #[cfg(feature = "async")]
+    pub fn polling(self) -> PollingWaiter<Delay> {
+        PollingWaiter(self)
+    }
+}
+
+/// A [DelayWaiter] that reads the busy pin's level every `delay_ms` instead of waiting for its
+/// edge, made with [DelayWaiter::polling]. The pin must implement the blocking `InputPin` too.
+#[cfg(feature = "async")]
+pub struct PollingWaiter<Delay>(DelayWaiter<Delay>);
+
+#[cfg(feature = "async")]
+impl<Delay, Busy> BusyWait<Busy> for DelayWaiter<Delay>
+where
+    Delay: DelayNs,
+    Busy: InputPin,
+{
+    async fn wait(&mut self, busy: &mut Busy) -> Result<Result<(), BusyTimeout>, Busy::Error> {
+        if self.timeout_ms == 0 {
+            return busy.wait_for_low().await.map(Ok);
+        }
+        match first(busy.wait_for_low(), self.delay.delay_ms(self.timeout_ms)).await {
+            Ok(result) => result.map(Ok),
+            Err(()) => Ok(Err(BusyTimeout)),
+        }
+    }
+}
+
+#[cfg(feature = "async")]
+impl<Delay, Busy> BusyWait<Busy> for PollingWaiter<Delay>
+where
+    Delay: DelayNs,
+    Busy: InputPin + embedded_hal::digital::InputPin,
+{
+    async fn wait(&mut self, busy: &mut Busy) -> Result<Result<(), BusyTimeout>, Busy::Error> {
+        let waiter = &mut self.0;
+        let mut elapsed_ms = 0u32;
+        loop {
+            waiter.delay.delay_ms(waiter.delay_ms).await;
+            elapsed_ms = elapsed_ms.saturating_add(waiter.delay_ms);
+            if !embedded_hal::digital::InputPin::is_high(busy)? {
+                return Ok(Ok(()));
+            }
+            if waiter.timeout_ms != 0 && elapsed_ms >= waiter.timeout_ms {
+                return Ok(Err(BusyTimeout));
+            }
+        }
+    }
+}
+
+/// Runs `a` and `b` until one of them finishes, returning `Ok` with the output of `a` or `Err`
+/// with the output of `b`.
+#[cfg(feature = "async")]
+async fn first<A: Future, B: Future>(a: A, b: B) -> Result<A::Output, B::Output> {
+    let mut a = pin!(a);
+    let mut b = pin!(b);
+    core::future::poll_fn(|cx| {
+        if let Poll::Ready(output) = a.as_mut().poll(cx) {
+            return Poll::Ready(Ok(output));
+        }
+        if let Poll::Ready(output) = b.as_mut().poll(cx) {
+            return Poll::Ready(Err(output));
+        }
+        Poll::Pending
+    })
+    .await
 }
 
 #[cfg(not(feature = "async"))]
 impl<Delay> BusyWait for DelayWaiter<Delay>
 where Delay: DelayNs {
+    fn start_wait(&mut self) {
+        self.elapsed_ms = 0;
+    }
+
     fn poll_wait(&mut self) -> Result<(), BusyTimeout> {
         self.delay.delay_ms(self.delay_ms);
+        self.elapsed_ms = self.elapsed_ms.saturating_add(self.delay_ms);
 
-        if self.timeout_ms != 0 {
-            match self.timeout_ms.checked_sub(self.delay_ms) {
-                Some(new_timeout) => {
-                    self.timeout_ms = new_timeout;
-                    Ok(())
-                }
-                None => {
-                    Err(BusyTimeout)
-                }
-            }
+        if self.timeout_ms != 0 && self.elapsed_ms > self.timeout_ms {
+            Err(BusyTimeout)
         } else {
             Ok(())
         }
     }
 }
 
-pub struct Display<C: IsDisplayConfiguration> {
+/// A snapshot of the bookkeeping a [Display] keeps about the controller.
+///
+/// This is plain data, so it can be stashed somewhere that survives the MCU's deep sleep (such as
+/// RTC memory) and handed to [Display::from_state] after waking up. As long as the panel itself
+/// stayed powered, this lets the first draw after waking use a partial refresh.
+#[repr(C)]
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct DisplayState {
     power_is_on: bool,
     initialized: bool,
     initial_refresh: bool,
     initial_write: bool,
-    #[cfg(not(feature = "async"))]
-    config: DisplayConfiguration<C::Spi, C::Dc, C::Rst, C::Busy, C::Delay, C::Wait>,
-    #[cfg(feature = "async")]
-    config: DisplayConfiguration<C::Spi, C::Dc, C::Rst, C::Busy, C::Delay>,
+    hibernating: bool,
+    partial_refreshes: u32,
+    partial_area: u32,
+    elapsed_ms: u32,
+    border: Border,
+    bw_option: RamOption,
+    red_option: RamOption,
+}
+
+impl DisplayState {
+    /// The state of a display that has not been touched yet.
+    pub const fn new() -> Self {
+        Self {
+            power_is_on: false,
+            initialized: false,
+            initial_refresh: true,
+            initial_write: true,
+            hibernating: false,
+            partial_refreshes: 0,
+            partial_area: 0,
+            elapsed_ms: 0,
+            border: Border::White,
+            bw_option: RamOption::Normal,
+            red_option: RamOption::Normal,
+        }
+    }
+}
+
+impl Default for DisplayState {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// Decides when a partial refresh is replaced with a full refresh to clear up ghosting.
+///
+/// The budgets count from the last full refresh, and are kept in [DisplayState] so that they carry
+/// over [Display::from_state].
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum RefreshPolicy {
+    /// Only do a full refresh when the panel needs one, or when asked with [Display::refresh_full].
+    Never,
+    /// Do a full refresh after this many partial refreshes.
+    AfterPartials(u32),
+    /// Do a full refresh once the partially refreshed area would exceed this many pixels.
+    AfterArea(u32),
+    /// Do a full refresh once this many milliseconds have been reported with [Display::elapse].
+    AfterMillis(u32),
+}
+
+/// What the panel drives its border with, set with [Display::set_border].
+#[repr(u8)]
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum Border {
+    /// Drive the border white on every refresh.
+    #[default]
+    White,
+    /// Drive the border black on every refresh.
+    Black,
+    /// Hold the border at VCOM, which leaves it as it is.
+    Vcom,
+    /// Leave the border floating.
+    HiZ,
+    /// Drive the border with the waveform's first lookup table, as the waveform decides.
+    FollowLut,
+    /// Any other border waveform control (0x3C) value, sent as it is.
+    Waveform(u8),
+}
+
+impl Border {
+    /// Returns the border waveform control (0x3C) value. White and black use the values most
+    /// panels need, see [Border::for_panel].
+    pub fn waveform(self) -> u8 {
+        match self {
+            Border::White => 0x05,
+            Border::Black => 0x02,
+            Border::Vcom => 0x80,
+            Border::HiZ => 0xc0,
+            Border::FollowLut => 0x04,
+            Border::Waveform(value) => value,
+        }
+    }
+
+    /// Replaces white and black with `P`'s own values for them.
+    pub fn for_panel<P: Panel>(self) -> Border {
+        match self {
+            Border::White => Border::Waveform(P::BORDER_WHITE),
+            Border::Black => Border::Waveform(P::BORDER_DARK),
+            border => border,
+        }
+    }
+}
+
+/// What [Display::diagnose] found out about the panel.
+///
+/// A missing panel or a broken cable shows up as `responding` being false, while a panel that
+/// keeps its busy line high shows up as `busy_responsive` being false. The detection results need
+/// the panel's data line to be readable, as for [Display::read_image], and are `None` without it.
+/// On boards where they can be read, they also confirm that a panel is there at all.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct PanelHealth {
+    /// Whether the busy line went high while the controller ran the detections, as it does when
+    /// it acts on a command.
+    pub responding: bool,
+    /// Whether the busy line went low again after each step within the busy wait's timeout.
+    pub busy_responsive: bool,
+    /// Whether HV ready detection found the booster's voltages ready, or `None` if the status
+    /// register could not be read back.
+    pub booster_ok: Option<bool>,
+    /// Whether VCI detection found the supply voltage above the detection level, or `None` if the
+    /// status register could not be read back.
+    pub vci_ok: Option<bool>,
+}
+
+/// The waveform currently in the controller's LUT register.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+enum Lut {
+    Otp,
+    Full,
+    Partial,
+    Grayscale,
+}
+
+pub struct Display<C: IsDisplayConfiguration, P: Panel = Gdeh0154d67> {
+    panel: PhantomData<P>,
+    state: DisplayState,
+    full_waveform: Option<&'static Waveform>,
+    partial_waveform: Option<&'static Waveform>,
+    lut: Lut,
+    temperature: Option<Temperature>,
+    refresh_policy: RefreshPolicy,
+    fill_chunk: usize,
+    config: Config<C>,
 }
 
 impl<C: IsDisplayConfiguration> Display<C> {
     pub fn new(config: C) -> Result<Self, Error<C>> {
+        Self::from_state(config, DisplayState::new())
+    }
+
+    /// Creates a display that picks up where the one that produced `state` left off.
+    pub fn from_state(config: C, state: DisplayState) -> Result<Self, Error<C>> {
+        Self::with_panel(config, Gdeh0154d67, state)
+    }
+}
+
+impl<C: IsDisplayConfiguration, P: Panel> Display<C, P> {
+    /// Creates a display for a panel other than the default [Gdeh0154d67], picking up from `state`
+    /// like [Display::from_state].
+    pub fn with_panel(config: C, _panel: P, state: DisplayState) -> Result<Self, Error<C>> {
         let mut config = config.get();
 
         do_output(config.dc.set_high())?;
         do_output(config.rst.set_high())?;
 
         Ok(Self {
-            initialized: false,
-            power_is_on: false,
-            initial_refresh: true,
-            initial_write: true,
+            panel: PhantomData,
+            state,
+            full_waveform: None,
+            partial_waveform: None,
+            lut: Lut::Otp,
+            temperature: None,
+            refresh_policy: RefreshPolicy::Never,
+            fill_chunk: 64,
             config,
         })
     }
 
+    /// Returns a snapshot of the display's state. See [DisplayState].
+    pub fn state(&self) -> DisplayState {
+        self.state
+    }
+
     #[cfg_attr(not(feature = "async"), remove_async_await::remove_async_await)]
     pub async fn reset(&mut self) -> Result<(), Error<C>> {
         do_output(self.config.rst.set_low())?;
         self.config.delay.delay_ms(10).await;
         do_output(self.config.rst.set_high())?;
         self.config.delay.delay_ms(10).await;
+        self.state.hibernating = false;
+        self.lut = Lut::Otp;
+
+        Ok(())
+    }
+
+    /// Sets the waveform used for full refreshes, or `None` to use the one in the panel's OTP.
+    /// The waveform is borrowed rather than copied into the display, so it can be one of the
+    /// presets such as `&Waveform::FULL`, or a `static` of its own.
+    pub fn set_full_waveform(&mut self, waveform: Option<&'static Waveform>) {
+        self.full_waveform = waveform;
+        if self.lut == Lut::Full {
+            self.lut = Lut::Otp;
+        }
+    }
+
+    /// Sets the waveform used for partial refreshes, or `None` to use the one in the panel's OTP.
+    pub fn set_partial_waveform(&mut self, waveform: Option<&'static Waveform>) {
+        self.partial_waveform = waveform;
+        if self.lut == Lut::Partial {
+            self.lut = Lut::Otp;
+        }
+    }
+
+    /// Measures the temperature with the controller's internal sensor.
+    #[cfg_attr(not(feature = "async"), remove_async_await::remove_async_await)]
+    pub async fn read_temperature(&mut self) -> Result<Temperature, Error<C>> {
+        if !self.state.initialized {
+            self.init().await?;
+        }
+
+        // Enable the clock, load the temperature and disable the clock again, so that the
+        // oscillator does not keep running until the next power off
+        self.send(Command::DisplayUpdateControl2(LOAD_TEMPERATURE)).await?;
+        self.send(Command::MasterActivation).await?;
+        self.wait_while_busy().await?;
+
+        let mut bytes = [0; 2];
+        self.read_register(Command::ReadTemperature, &mut bytes).await?;
+
+        Ok(Temperature::from_register(bytes))
+    }
+
+    /// Reads back the controller's RAM, which needs the panel's data line to be readable (MISO,
+    /// or a 3-wire bus that the SPI device turns around). The bitmap has the same layout as the
+    /// one passed to [Display::draw_image], and `x_lo` and `x_hi` must be multiples of 8 so that
+    /// it lines up with the RAM. The area must be on the screen.
+    #[cfg_attr(not(feature = "async"), remove_async_await::remove_async_await)]
+    pub async fn read_image(
+        &mut self,
+        ram: Ram,
+        bitmap: &mut [u8],
+        x_lo: i16,
+        y_lo: i16,
+        x_hi: i16,
+        y_hi: i16,
+    ) -> Result<(), Error<C>> {
+        packed_stride(bitmap, x_lo, y_lo, x_hi, y_hi)?;
+        let rect = Rect {
+            x: Span { lo: x_lo, hi: x_hi },
+            y: Span { lo: y_lo, hi: y_hi },
+        };
+        let screen = screen_rect::<P>();
+        let on_screen = x_lo >= 0
+            && y_lo >= 0
+            && x_hi <= ceil_multiple(screen.x.hi, 8)
+            && y_hi <= screen.y.hi;
+        if x_lo % 8 != 0 || x_hi % 8 != 0 || !on_screen {
+            return Err(DisplayError::InvalidBitmap);
+        }
+        if bitmap.is_empty() {
+            return Ok(());
+        }
+        if !self.state.initialized {
+            self.init().await?;
+        }
+
+        self.set_partial_ram_area(rect).await?;
+        self.send(Command::RamReadOption(ram)).await?;
+        self.send(Command::ReadRam).await?;
+        // The first byte read is a dummy
+        self.config
+            .spi
+            .transaction(&mut [Operation::Read(&mut [0]), Operation::Read(bitmap)])
+            .await?;
+
+        Ok(())
+    }
+
+    /// Reads the status register. See [Display::read_image] for the wiring this needs.
+    ///
+    /// Like the other reads, this wakes the controller with [Display::reset] if it is hibernating,
+    /// since it does not answer in deep sleep.
+    #[cfg_attr(not(feature = "async"), remove_async_await::remove_async_await)]
+    pub async fn read_status(&mut self) -> Result<Status, Error<C>> {
+        let mut byte = [0];
+        self.read_register(Command::ReadStatus, &mut byte).await?;
+
+        Ok(Status(byte[0]))
+    }
+
+    /// Reads the 10 byte user ID programmed into the panel's OTP. See [Display::read_image] for
+    /// the wiring this needs.
+    #[cfg_attr(not(feature = "async"), remove_async_await::remove_async_await)]
+    pub async fn read_user_id(&mut self) -> Result<[u8; 10], Error<C>> {
+        let mut bytes = [0; 10];
+        self.read_register(Command::ReadUserId, &mut bytes).await?;
+
+        Ok(bytes)
+    }
+
+    /// Reads the 11 bytes of display options programmed into the panel's OTP: the VCOM settings,
+    /// display mode and waveform version. See [Display::read_image] for the wiring this needs.
+    #[cfg_attr(not(feature = "async"), remove_async_await::remove_async_await)]
+    pub async fn read_display_option(&mut self) -> Result<[u8; 11], Error<C>> {
+        let mut bytes = [0; 11];
+        self.read_register(Command::ReadDisplayOption, &mut bytes).await?;
+
+        Ok(bytes)
+    }
+
+    /// Checks the panel: turns it on, runs the controller's HV ready and VCI detections, and reads
+    /// back their results. Busy timeouts are reported in the [PanelHealth] instead of failing, so
+    /// that a panel that is missing can be told apart from one that is busy forever.
+    ///
+    /// Once one busy wait has timed out, the busy line is taken to be stuck and the remaining
+    /// waits are skipped, so a stuck panel costs a single timeout rather than one per step.
+    #[cfg_attr(not(feature = "async"), remove_async_await::remove_async_await)]
+    pub async fn diagnose(&mut self) -> Result<PanelHealth, Error<C>> {
+        let mut busy_responsive = true;
+        if !self.state.initialized {
+            busy_responsive &= settled(self.init().await)?;
+        }
+
+        // One loop with the shortest cool down
+        self.send(Command::HvReadyDetection(0x00)).await?;
+        let mut responding = self.busy_asserted().await?;
+        if busy_responsive {
+            busy_responsive = settled(self.wait_while_busy().await)?;
+        }
+        let booster = self.read_status().await?;
+
+        // The controller's default detection level
+        self.send(Command::VciDetection(0x03)).await?;
+        responding &= self.busy_asserted().await?;
+        if busy_responsive {
+            busy_responsive = settled(self.wait_while_busy().await)?;
+        }
+        let supply = self.read_status().await?;
+
+        // A data line that cannot be read gives all zeros or all ones, neither of which has the
+        // chip ID the controller reports
+        let readable = booster.chip_id() == 0b01 && supply.chip_id() == 0b01;
+        Ok(PanelHealth {
+            responding,
+            busy_responsive,
+            booster_ok: readable.then(|| !booster.hv_not_ready()),
+            vci_ok: readable.then(|| !supply.vci_low()),
+        })
+    }
+
+    /// Sets the temperature used to pick the waveform from the panel's OTP, for example from a
+    /// sensor that is closer to the panel than the controller is. `None` goes back to using the
+    /// controller's internal sensor.
+    pub fn set_temperature(&mut self, temperature: Option<Temperature>) {
+        self.temperature = temperature;
+    }
+
+    pub fn set_refresh_policy(&mut self, policy: RefreshPolicy) {
+        self.refresh_policy = policy;
+    }
+
+    /// Sets how many bytes are sent per SPI transaction when filling the whole RAM, as in
+    /// [Display::clear_screen]. The default is 64, and values are limited to 1 to
+    /// [MAX_FILL_CHUNK]. Each transaction repeats writes from one small buffer, so larger chunks
+    /// do not take more stack.
+    pub fn set_fill_chunk(&mut self, bytes: usize) {
+        self.fill_chunk = bytes.clamp(1, MAX_FILL_CHUNK);
+    }
+
+    /// Tells the display that `ms` milliseconds have passed, for [RefreshPolicy::AfterMillis].
+    pub fn elapse(&mut self, ms: u32) {
+        self.state.elapsed_ms = self.state.elapsed_ms.saturating_add(ms);
+    }
+
+    /// Refreshes the whole screen with a full refresh, showing whatever is in the controller's RAM.
+    #[cfg_attr(not(feature = "async"), remove_async_await::remove_async_await)]
+    pub async fn refresh_full(&mut self) -> Result<(), Error<C>> {
+        if !self.state.initialized {
+            self.init().await?;
+        }
+        self.update_full().await?;
 
         Ok(())
     }
@@ -277,6 +729,13 @@ impl<C: IsDisplayConfiguration> Display<C> {
         Ok(())
     }
 
+    /// Draws `bitmap` to the given area of the screen. The bitmap has one bit per pixel, 1 being
+    /// white, with each row starting on a new byte, so it must be exactly
+    /// `(x_hi - x_lo + 7) / 8 * (y_hi - y_lo)` bytes long.
+    ///
+    /// Parts of the image that are off the screen are cut off. The controller can only be written
+    /// in whole bytes, so if `x_lo` or `x_hi` is not a multiple of 8, the pixels next to the image
+    /// that share a byte with it are set to white.
     #[cfg_attr(not(feature = "async"), remove_async_await::remove_async_await)]
     pub async fn draw_image(
         &mut self,
@@ -285,79 +744,222 @@ impl<C: IsDisplayConfiguration> Display<C> {
         y_lo: i16,
         x_hi: i16,
         y_hi: i16,
+    ) -> Result<(), Error<C>> {
+        let stride = packed_stride(bitmap, x_lo, y_lo, x_hi, y_hi)?;
+        self.draw_image_strided(bitmap, stride, x_lo, y_lo, x_hi, y_hi)
+            .await
+    }
+
+    /// Like [Display::draw_image], but with rows that start every `stride` bytes. This allows
+    /// drawing part of a larger image; the last row only needs to be as long as the image is wide.
+    #[cfg_attr(not(feature = "async"), remove_async_await::remove_async_await)]
+    pub async fn draw_image_strided(
+        &mut self,
+        bitmap: &[u8],
+        stride: usize,
+        x_lo: i16,
+        y_lo: i16,
+        x_hi: i16,
+        y_hi: i16,
     ) -> Result<(), Error<C>> {
         let rect = Rect {
             x: Span { lo: x_lo, hi: x_hi },
             y: Span { lo: y_lo, hi: y_hi },
         };
-        self.write_image(bitmap, x_lo, y_lo, x_hi, y_hi).await?;
+        self.write_image_inner(Ram::BlackWhite, bitmap, stride, rect, 0x00).await?;
         self.refresh(rect).await?;
-        self.write_image_again(bitmap, x_lo, y_lo, x_hi, y_hi).await?;
+        self.write_image_again(bitmap, stride, rect).await?;
 
         Ok(())
     }
 
+    /// Draws a four-level grayscale image, given as two bit planes in the same layout as the
+    /// bitmap passed to [Display::draw_image]. For each pixel, `low` holds the low bit and `high`
+    /// the high bit of its gray level, where 0 is black and 3 is white.
+    ///
+    /// This always does a full refresh with [Waveform::GRAYSCALE]. Since it overwrites the RAM
+    /// that partial refreshes compare against, the next black and white image is drawn with a
+    /// full refresh too. That refresh only rewrites the area of the new image, so a `Framebuffer`
+    /// or `ShadowFramebuffer` that was flushed before must be `invalidate()`d to cover the
+    /// grayscale image again.
+    ///
+    /// Tri-color panels use the second RAM for red, so they return [DisplayError::Unsupported].
     #[cfg_attr(not(feature = "async"), remove_async_await::remove_async_await)]
-    pub async fn write_image(
+    pub async fn draw_image_gray2(
         &mut self,
-        bitmap: &[u8],
+        low: &[u8],
+        high: &[u8],
         x_lo: i16,
         y_lo: i16,
         x_hi: i16,
         y_hi: i16,
     ) -> Result<(), Error<C>> {
+        if P::FEATURES.tri_color {
+            return Err(DisplayError::Unsupported);
+        }
+        let stride = packed_stride(low, x_lo, y_lo, x_hi, y_hi)?;
+        packed_stride(high, x_lo, y_lo, x_hi, y_hi)?;
         let rect = Rect {
             x: Span { lo: x_lo, hi: x_hi },
             y: Span { lo: y_lo, hi: y_hi },
         };
-        self.write_image_inner(0x24, bitmap, rect).await?;
+        self.write_image_inner(Ram::BlackWhite, low, stride, rect, 0x00).await?;
+        self.write_image_inner(Ram::Red, high, stride, rect, 0x00).await?;
+        self.update_grayscale().await?;
+
         Ok(())
     }
 
+    /// Draws a black, white and red image on a tri-color panel, given as two bitmaps in the same
+    /// layout as the one passed to [Display::draw_image]. A 0 bit in `black` makes the pixel
+    /// black, and a 0 bit in `red` makes it red, which wins over black.
+    ///
+    /// Tri-color panels cannot do partial refreshes, so this always does a full refresh. On other
+    /// panels, this returns [DisplayError::Unsupported].
     #[cfg_attr(not(feature = "async"), remove_async_await::remove_async_await)]
-    async fn write_image_again(
+    pub async fn draw_image_bwr(
+        &mut self,
+        black: &[u8],
+        red: &[u8],
+        x_lo: i16,
+        y_lo: i16,
+        x_hi: i16,
+        y_hi: i16,
+    ) -> Result<(), Error<C>> {
+        if !P::FEATURES.tri_color {
+            return Err(DisplayError::Unsupported);
+        }
+        let stride = packed_stride(black, x_lo, y_lo, x_hi, y_hi)?;
+        packed_stride(red, x_lo, y_lo, x_hi, y_hi)?;
+        let rect = Rect {
+            x: Span { lo: x_lo, hi: x_hi },
+            y: Span { lo: y_lo, hi: y_hi },
+        };
+        self.write_image_inner(Ram::BlackWhite, black, stride, rect, 0x00).await?;
+        // The red RAM uses 1 for red
+        self.write_image_inner(Ram::Red, red, stride, rect, 0xFF).await?;
+        self.update_full().await?;
+
+        Ok(())
+    }
+
+    /// Writes `bitmap` to the controller's RAM like [Display::draw_image], without refreshing.
+    #[cfg_attr(not(feature = "async"), remove_async_await::remove_async_await)]
+    pub async fn write_image(
         &mut self,
         bitmap: &[u8],
         x_lo: i16,
         y_lo: i16,
         x_hi: i16,
         y_hi: i16,
+    ) -> Result<(), Error<C>> {
+        let stride = packed_stride(bitmap, x_lo, y_lo, x_hi, y_hi)?;
+        self.write_image_strided(bitmap, stride, x_lo, y_lo, x_hi, y_hi)
+            .await
+    }
+
+    /// Writes `bitmap` to the controller's RAM like [Display::draw_image_strided], without
+    /// refreshing.
+    #[cfg_attr(not(feature = "async"), remove_async_await::remove_async_await)]
+    pub async fn write_image_strided(
+        &mut self,
+        bitmap: &[u8],
+        stride: usize,
+        x_lo: i16,
+        y_lo: i16,
+        x_hi: i16,
+        y_hi: i16,
     ) -> Result<(), Error<C>> {
         let rect = Rect {
             x: Span { lo: x_lo, hi: x_hi },
             y: Span { lo: y_lo, hi: y_hi },
         };
-        self.write_image_inner(0x24, bitmap, rect).await?;
+        self.write_image_inner(Ram::BlackWhite, bitmap, stride, rect, 0x00).await?;
         Ok(())
     }
 
+    #[cfg_attr(not(feature = "async"), remove_async_await::remove_async_await)]
+    async fn write_image_again(
+        &mut self,
+        bitmap: &[u8],
+        stride: usize,
+        rect: Rect,
+    ) -> Result<(), Error<C>> {
+        self.write_image_inner(Ram::BlackWhite, bitmap, stride, rect, 0x00).await?;
+        Ok(())
+    }
+
+    /// Writes the part of `bitmap` that is on the screen to `ram`, with every byte XORed with
+    /// `mask`.
     #[cfg_attr(not(feature = "async"), remove_async_await::remove_async_await)]
     async fn write_image_inner(
         &mut self,
-        command: u8,
+        ram: Ram,
         bitmap: &[u8],
+        stride: usize,
         rect: Rect,
+        mask: u8,
     ) -> Result<(), Error<C>> {
-        if self.initial_write {
+        if !bitmap_fits(bitmap, stride, rect) {
+            return Err(DisplayError::InvalidBitmap);
+        }
+
+        if self.state.initial_write {
             self.write_screen_buffer(0xFF).await?;
         }
+        if !self.state.initialized {
+            self.init().await?;
+        }
 
-        let Some(screen_rect) = rect.intersection(SCREEN_RECT) else {
+        let Some(screen_rect) = rect.intersection(screen_rect::<P>()) else {
             return Ok(());
         };
+        if screen_rect.x.size() == 0 || screen_rect.y.size() == 0 {
+            return Ok(());
+        }
 
         let aligned_rect = Rect {
             x: Span {
                 lo: floor_multiple(screen_rect.x.lo, 8),
-                hi: ceil_multiple(screen_rect.x.lo + screen_rect.x.size(), 8),
+                hi: ceil_multiple(screen_rect.x.hi, 8),
             },
             ..screen_rect
         };
 
         self.set_partial_ram_area(aligned_rect).await?;
 
-        self.transfer_command(command).await?;
-        self.config.spi.write(bitmap).await?;
+        self.send(Command::WriteRam(ram, &[])).await?;
+        for y in screen_rect.y.lo..screen_rect.y.hi {
+            let row = &bitmap[(y - rect.y.lo) as usize * stride..];
+
+            if rect.x.lo % 8 == 0 && screen_rect.x.hi % 8 == 0 && mask == 0x00 {
+                // The rows line up with the controller's bytes, so they can be sent as they are
+                let start = (aligned_rect.x.lo - rect.x.lo) as usize / 8;
+                let end = start + aligned_rect.x.size() as usize / 8;
+                self.config.spi.write(&row[start..end]).await?;
+                continue;
+            }
+
+            let mut chunk = [0; 32];
+            let mut len = 0;
+            for x in (aligned_rect.x.lo..aligned_rect.x.hi).step_by(8) {
+                chunk[len] = mask
+                    ^ clipped_byte(
+                        row,
+                        x - rect.x.lo,
+                        screen_rect.x.lo - x,
+                        x + 8 - screen_rect.x.hi,
+                    );
+                len += 1;
+                if len == chunk.len() {
+                    self.config.spi.write(&chunk).await?;
+                    len = 0;
+                }
+            }
+            if len > 0 {
+                self.config.spi.write(&chunk[..len]).await?;
+            }
+        }
 
         Ok(())
     }
@@ -366,25 +968,37 @@ impl<C: IsDisplayConfiguration> Display<C> {
     async fn init(&mut self) -> Result<(), Error<C>> {
         self.init_display().await?;
         self.power_on().await?;
-        self.initialized = true;
+        self.state.initialized = true;
         Ok(())
     }
 
     #[cfg_attr(not(feature = "async"), remove_async_await::remove_async_await)]
     async fn init_display(&mut self) -> Result<(), Error<C>> {
-        // TODO:   if (_hibernating) _reset();
+        if self.state.hibernating {
+            self.reset().await?;
+        }
 
-        self.transfer_command(0x01).await?;
-        self.config.spi.write(&[0xC7, 0x00, 0x00]).await?;
+        let [lo, hi, scanning] = P::DRIVER_OUTPUT_CONTROL;
+        self.send(Command::DriverOutputControl {
+            last_gate: u16::from_le_bytes([lo, hi]),
+            scanning,
+        })
+        .await?;
+
+        if P::SOURCE_OUTPUT_MODE != 0x00
+            || self.state.bw_option != RamOption::Normal
+            || self.state.red_option != RamOption::Normal
+        {
+            self.send_ram_options().await?;
+        }
 
         // TODO: if(reduceBoosterTime) {...}
 
-        self.transfer_command(0x18).await?;
-        self.config.spi.write(&[0x80]).await?;
+        self.send(Command::TemperatureSensor(0x80)).await?;
 
-        self.set_dark_border(false).await?;
+        self.set_border(self.state.border).await?;
 
-        self.set_partial_ram_area(SCREEN_RECT).await?;
+        self.set_partial_ram_area(screen_rect::<P>()).await?;
 
         Ok(())
     }
@@ -392,26 +1006,75 @@ impl<C: IsDisplayConfiguration> Display<C> {
     #[cfg_attr(not(feature = "async"), remove_async_await::remove_async_await)]
     async fn power_on(&mut self) -> Result<(), Error<C>> {
         //TODO: if(waitingPowerOn)
-        if self.power_is_on {
+        if self.state.power_is_on {
             return Ok(());
         }
 
-        self.transfer_command(0x22).await?;
-        self.config.spi.write(&[0xf8]).await?;
-        self.transfer_command(0x20).await?;
+        self.send(Command::DisplayUpdateControl2(P::POWER_ON)).await?;
+        self.send(Command::MasterActivation).await?;
         self.wait_while_busy().await?;
-        self.power_is_on = true;
+        self.state.power_is_on = true;
+        self.lut = Lut::Otp;
 
         Ok(())
     }
 
+    /// Sets what the border is driven with from the next refresh on. The default is
+    /// [Border::White], and the setting is sent again whenever the controller is set up after
+    /// [Display::power_off] or [Display::hibernate]. It is kept in [DisplayState], so it carries
+    /// over [Display::from_state].
     #[cfg_attr(not(feature = "async"), remove_async_await::remove_async_await)]
-    async fn set_dark_border(&mut self, dark_border: bool) -> Result<(), Error<C>> {
-        //TODO: if(_hibernating)return;
-        self.transfer_command(0x3C).await?;
-        self.config
-            .spi
-            .write(&[if dark_border { 0x02 } else { 0x05 }])
+    pub async fn set_border(&mut self, border: Border) -> Result<(), Error<C>> {
+        self.state.border = border;
+        if self.state.hibernating {
+            return Ok(());
+        }
+        self.send(Command::BorderWaveform(border.for_panel::<P>())).await?;
+
+        Ok(())
+    }
+
+    /// Sets how the controller reads the black and white RAM and the red RAM during refreshes,
+    /// which changes what is shown without sending the image again. Changing the options makes
+    /// the next refresh a full one, since the red RAM holds the previous image as it was shown
+    /// before. Like [Display::set_border], the options are sent again whenever the controller is
+    /// set up, and are kept in [DisplayState].
+    #[cfg_attr(not(feature = "async"), remove_async_await::remove_async_await)]
+    pub async fn set_ram_options(&mut self, bw: RamOption, red: RamOption) -> Result<(), Error<C>> {
+        if (bw, red) == (self.state.bw_option, self.state.red_option) {
+            return Ok(());
+        }
+        self.state.bw_option = bw;
+        self.state.red_option = red;
+        self.state.initial_refresh = true;
+        if self.state.hibernating {
+            return Ok(());
+        }
+        self.send_ram_options().await?;
+
+        Ok(())
+    }
+
+    /// Shows the image inverted from the next refresh on, or back to normal. The red plane of
+    /// tri-color panels is left alone. For a dark mode, follow this with [Display::refresh_full].
+    #[cfg_attr(not(feature = "async"), remove_async_await::remove_async_await)]
+    pub async fn set_inverted(&mut self, inverted: bool) -> Result<(), Error<C>> {
+        let option = match inverted {
+            true => RamOption::Inverse,
+            false => RamOption::Normal,
+        };
+        if P::FEATURES.tri_color {
+            self.set_ram_options(option, self.state.red_option).await
+        } else {
+            // The previous image is compared with the new one, so both are inverted alike
+            self.set_ram_options(option, option).await
+        }
+    }
+
+    #[cfg_attr(not(feature = "async"), remove_async_await::remove_async_await)]
+    async fn send_ram_options(&mut self) -> Result<(), Error<C>> {
+        let options = (self.state.red_option.bits() << 4) | self.state.bw_option.bits();
+        self.send(Command::DisplayUpdateControl1([options, P::SOURCE_OUTPUT_MODE]))
             .await?;
 
         Ok(())
@@ -419,16 +1082,32 @@ impl<C: IsDisplayConfiguration> Display<C> {
 
     #[cfg_attr(not(feature = "async"), remove_async_await::remove_async_await)]
     pub async fn power_off(&mut self) -> Result<(), Error<C>> {
-        if !self.power_is_on {
+        if !self.state.power_is_on {
             return Ok(());
         }
 
-        self.transfer_command(0x22).await?;
-        self.config.spi.write(&[0x83]).await?;
-        self.transfer_command(0x20).await?;
+        self.send(Command::DisplayUpdateControl2(P::POWER_OFF)).await?;
+        self.send(Command::MasterActivation).await?;
         self.wait_while_busy().await?;
-        self.power_is_on = false;
-        self.initialized = false;
+        self.state.power_is_on = false;
+        self.state.initialized = false;
+
+        Ok(())
+    }
+
+    /// Powers off the display and puts the controller into deep sleep, which draws much less
+    /// current than [Display::power_off] alone. The RAM contents are retained, and the next
+    /// operation that needs the controller wakes it up again through [Display::reset].
+    #[cfg_attr(not(feature = "async"), remove_async_await::remove_async_await)]
+    pub async fn hibernate(&mut self) -> Result<(), Error<C>> {
+        if self.state.hibernating {
+            return Ok(());
+        }
+
+        self.power_off().await?;
+        self.send(Command::DeepSleep(DeepSleep::Mode1)).await?;
+        self.state.hibernating = true;
+        self.state.initialized = false;
 
         Ok(())
     }
@@ -436,7 +1115,7 @@ impl<C: IsDisplayConfiguration> Display<C> {
     #[cfg_attr(not(feature = "async"), remove_async_await::remove_async_await)]
     async fn refresh_all(&mut self, partial_update_mode: bool) -> Result<(), Error<C>> {
         if partial_update_mode {
-            self.refresh(SCREEN_RECT).await?;
+            self.refresh(screen_rect::<P>()).await?;
         } else {
             self.update_full().await?;
         }
@@ -446,13 +1125,15 @@ impl<C: IsDisplayConfiguration> Display<C> {
 
     #[cfg_attr(not(feature = "async"), remove_async_await::remove_async_await)]
     async fn refresh(&mut self, rect: Rect) -> Result<(), Error<C>> {
-        if self.initial_refresh {
-            return self.update_full().await;
-        }
-        let rect = rect.intersection(SCREEN_RECT);
-        let Some(rect) = rect else {
+        let Some(rect) = rect.intersection(screen_rect::<P>()) else {
             return Ok(());
         };
+        if rect.x.size() == 0 || rect.y.size() == 0 {
+            return Ok(());
+        }
+        if self.state.initial_refresh {
+            return self.update_full().await;
+        }
         let rect = Rect {
             x: Span {
                 lo: floor_multiple(rect.x.lo, 8),
@@ -460,22 +1141,52 @@ impl<C: IsDisplayConfiguration> Display<C> {
             },
             y: rect.y,
         };
-        if !self.initialized {
+        if !self.state.initialized {
             self.init().await?;
         }
+
+        let area = rect.x.size() as u32 * rect.y.size() as u32;
+        let needs_full = !P::FEATURES.partial_refresh || match self.refresh_policy {
+            RefreshPolicy::Never => false,
+            RefreshPolicy::AfterPartials(n) => self.state.partial_refreshes >= n,
+            RefreshPolicy::AfterArea(budget) => {
+                self.state.partial_area.saturating_add(area) > budget
+            }
+            RefreshPolicy::AfterMillis(budget) => self.state.elapsed_ms >= budget,
+        };
+        if needs_full {
+            return self.update_full().await;
+        }
+
         self.set_partial_ram_area(rect).await?;
         self.update_part().await?;
+        self.state.partial_refreshes = self.state.partial_refreshes.saturating_add(1);
+        self.state.partial_area = self.state.partial_area.saturating_add(area);
 
         Ok(())
     }
 
     #[cfg_attr(not(feature = "async"), remove_async_await::remove_async_await)]
     async fn update_full(&mut self) -> Result<(), Error<C>> {
-        self.initial_refresh = false;
+        self.state.initial_refresh = false;
+        self.state.partial_refreshes = 0;
+        self.state.partial_area = 0;
+        self.state.elapsed_ms = 0;
+
+        let control = match self.full_waveform {
+            Some(waveform) => {
+                self.use_waveform(Lut::Full, waveform).await?;
+                UPDATE_FULL_LUT
+            }
+            None => {
+                self.lut = Lut::Otp;
+                P::UPDATE_FULL
+            }
+        };
+        let control = self.with_temperature(control).await?;
 
-        self.transfer_command(0x22).await?;
-        self.config.spi.write(&[0xf4]).await?;
-        self.transfer_command(0x20).await?;
+        self.send(Command::DisplayUpdateControl2(control)).await?;
+        self.send(Command::MasterActivation).await?;
         self.wait_while_busy().await?;
 
         Ok(())
@@ -483,70 +1194,133 @@ impl<C: IsDisplayConfiguration> Display<C> {
 
     #[cfg_attr(not(feature = "async"), remove_async_await::remove_async_await)]
     async fn update_part(&mut self) -> Result<(), Error<C>> {
-        self.transfer_command(0x22).await?;
-        self.config.spi.write(&[0xfc]).await?;
-        self.transfer_command(0x20).await?;
+        let control = match self.partial_waveform {
+            Some(waveform) => {
+                self.use_waveform(Lut::Partial, waveform).await?;
+                UPDATE_PARTIAL_LUT
+            }
+            None => {
+                self.lut = Lut::Otp;
+                P::UPDATE_PARTIAL
+            }
+        };
+        let control = self.with_temperature(control).await?;
+
+        self.send(Command::DisplayUpdateControl2(control)).await?;
+        self.send(Command::MasterActivation).await?;
         self.wait_while_busy().await?;
 
         Ok(())
     }
 
+    /// Writes the temperature set with [Display::set_temperature], if any, and adjusts the display
+    /// update `control` value so that the controller does not overwrite it with its own reading.
     #[cfg_attr(not(feature = "async"), remove_async_await::remove_async_await)]
-    async fn set_partial_ram_area(&mut self, rect: Rect) -> Result<(), Error<C>> {
-        self.transfer_command(0x11).await?;
-        self.config.spi.write(&[0x03]).await?;
-        self.transfer_command(0x44).await?;
-        self.config
-            .spi
-            .write(&[(rect.x.lo / 8) as u8, ((rect.x.hi - 1) / 8) as u8])
-            .await?;
-        self.transfer_command(0x45).await?;
-        self.config.spi.write(&[
-            (rect.y.lo % 256) as u8,
-            (rect.y.lo / 256) as u8,
-            ((rect.y.hi - 1) % 256) as u8,
-            ((rect.y.hi - 1) % 256) as u8,
-        ]).await?;
-        self.transfer_command(0x4e).await?;
-        self.config.spi.write(&[(rect.x.lo / 8) as u8]).await?;
-        self.transfer_command(0x4f).await?;
-        self.config
-            .spi
-            .write(&[(rect.y.lo % 256) as u8, (rect.y.lo / 256) as u8])
+    async fn with_temperature(&mut self, control: u8) -> Result<u8, Error<C>> {
+        let Some(temperature) = self.temperature else {
+            return Ok(control);
+        };
+
+        self.send(Command::WriteTemperature(temperature)).await?;
+
+        Ok(control & !0x20)
+    }
+
+    #[cfg_attr(not(feature = "async"), remove_async_await::remove_async_await)]
+    async fn update_grayscale(&mut self) -> Result<(), Error<C>> {
+        self.use_waveform(Lut::Grayscale, &Waveform::GRAYSCALE)
             .await?;
 
+        self.send(Command::DisplayUpdateControl2(UPDATE_FULL_LUT)).await?;
+        self.send(Command::MasterActivation).await?;
+        self.wait_while_busy().await?;
+
+        self.state.initial_write = true;
+        self.state.initial_refresh = true;
+
+        Ok(())
+    }
+
+    /// Makes sure `waveform` is in the LUT register, writing it only if `lut` is not already there.
+    #[cfg_attr(not(feature = "async"), remove_async_await::remove_async_await)]
+    async fn use_waveform(&mut self, lut: Lut, waveform: &Waveform) -> Result<(), Error<C>> {
+        if self.lut == lut {
+            return Ok(());
+        }
+
+        self.send(Command::WriteLut(&waveform.lut)).await?;
+        self.wait_while_busy().await?;
+        self.send(Command::EndOption(waveform.end_option)).await?;
+        self.send(Command::GateVoltage(waveform.gate_voltage)).await?;
+        self.send(Command::SourceVoltage(waveform.source_voltage)).await?;
+        self.send(Command::WriteVcom(waveform.vcom)).await?;
+        self.lut = lut;
+
+        Ok(())
+    }
+
+    #[cfg_attr(not(feature = "async"), remove_async_await::remove_async_await)]
+    async fn set_partial_ram_area(&mut self, rect: Rect) -> Result<(), Error<C>> {
+        self.send(Command::DataEntryMode(DataEntryMode::IncrementXY)).await?;
+        self.send(Command::RamXAddress {
+            start: (rect.x.lo / 8) as u8,
+            end: ((rect.x.hi - 1) / 8) as u8,
+        })
+        .await?;
+        self.send(Command::RamYAddress {
+            start: rect.y.lo as u16,
+            end: (rect.y.hi - 1) as u16,
+        })
+        .await?;
+        self.send(Command::RamXCounter((rect.x.lo / 8) as u8)).await?;
+        self.send(Command::RamYCounter(rect.y.lo as u16)).await?;
+
         Ok(())
     }
 
     #[cfg_attr(not(feature = "async"), remove_async_await::remove_async_await)]
     async fn write_screen_buffer(&mut self, value: u8) -> Result<(), Error<C>> {
-        if !self.initialized {
+        if !self.state.initialized {
             self.init().await?;
         }
-        if self.initial_write {
-            self.write_screen_buffer_inner(0x26, value).await?;
+        if P::FEATURES.tri_color {
+            // This is the red plane rather than the previous image, so clearing empties it
+            self.write_screen_buffer_inner(Ram::Red, 0x00).await?;
+        } else if self.state.initial_write {
+            self.write_screen_buffer_inner(Ram::Red, value).await?;
         }
-        self.write_screen_buffer_inner(0x24, value).await?;
-        self.initial_write = false;
+        self.write_screen_buffer_inner(Ram::BlackWhite, value).await?;
+        self.state.initial_write = false;
 
         Ok(())
     }
 
     #[cfg_attr(not(feature = "async"), remove_async_await::remove_async_await)]
     async fn write_screen_buffer_again(&mut self, value: u8) -> Result<(), Error<C>> {
-        if !self.initialized {
+        if !self.state.initialized {
             self.init().await?;
         }
-        self.write_screen_buffer_inner(0x24, value).await?;
+        self.write_screen_buffer_inner(Ram::BlackWhite, value).await?;
 
         Ok(())
     }
 
     #[cfg_attr(not(feature = "async"), remove_async_await::remove_async_await)]
-    async fn write_screen_buffer_inner(&mut self, command: u8, value: u8) -> Result<(), Error<C>> {
-        self.transfer_command(command).await?;
-        for _ in 0..WIDTH * HEIGHT / 8 {
-            self.config.spi.write(&[value]).await?;
+    async fn write_screen_buffer_inner(&mut self, ram: Ram, value: u8) -> Result<(), Error<C>> {
+        self.send(Command::WriteRam(ram, &[])).await?;
+        let buffer = [value; FILL_BUFFER];
+        let mut remaining = stride::<P>() * P::HEIGHT as usize;
+        while remaining > 0 {
+            let len = remaining.min(self.fill_chunk);
+            let mut operations: [_; MAX_FILL_CHUNK / FILL_BUFFER] = core::array::from_fn(|i| {
+                let written = (i * FILL_BUFFER).min(len);
+                Operation::Write(&buffer[..(len - written).min(FILL_BUFFER)])
+            });
+            self.config
+                .spi
+                .transaction(&mut operations[..len.div_ceil(FILL_BUFFER)])
+                .await?;
+            remaining -= len;
         }
 
         Ok(())
@@ -557,6 +1331,8 @@ impl<C: IsDisplayConfiguration> Display<C> {
         // Give some time for `busy` to be asserted by the display
         self.config.delay.delay_ms(1).await;
 
+        #[cfg(not(feature = "async"))]
+        self.config.busy_wait.start_wait();
         #[cfg(not(feature = "async"))]
         while do_input(self.config.busy.is_high())? {
             match self.config.busy_wait.poll_wait() {
@@ -568,14 +1344,63 @@ impl<C: IsDisplayConfiguration> Display<C> {
             }
         }
         #[cfg(feature = "async")]
-        match self.config.busy.wait_for_low().await {
-            Ok(()) => (),
+        match self.config.busy_wait.wait(&mut self.config.busy).await {
+            Ok(Ok(())) => (),
+            Ok(Err(BusyTimeout)) => return Err(DisplayError::BusyTimeout),
             Err(e) => return Err(DisplayError::Input(e)),
         };
 
         Ok(())
     }
 
+    /// Returns whether the busy line goes high within a millisecond, as it does while the
+    /// controller works on a command.
+    #[cfg(not(feature = "async"))]
+    fn busy_asserted(&mut self) -> Result<bool, Error<C>> {
+        self.config.delay.delay_ms(1);
+        do_input(self.config.busy.is_high())
+    }
+    #[cfg(feature = "async")]
+    async fn busy_asserted(&mut self) -> Result<bool, Error<C>> {
+        match first(self.config.busy.wait_for_high(), self.config.delay.delay_ms(1)).await {
+            Ok(result) => result.map(|()| true).map_err(DisplayError::Input),
+            Err(()) => Ok(false),
+        }
+    }
+
+    /// Sends `command` to the controller as it is, for experimenting with registers. See
+    /// [command].
+    #[cfg_attr(not(feature = "async"), remove_async_await::remove_async_await)]
+    pub async fn send(&mut self, command: Command<'_>) -> Result<(), Error<C>> {
+        let mut buf = [0; 4];
+        let params = command.params(&mut buf);
+        self.transfer_command(command.code()).await?;
+        if !params.is_empty() {
+            self.config.spi.write(params).await?;
+        }
+
+        Ok(())
+    }
+
+    #[cfg_attr(not(feature = "async"), remove_async_await::remove_async_await)]
+    async fn read_register(
+        &mut self,
+        command: Command<'_>,
+        buf: &mut [u8],
+    ) -> Result<(), Error<C>> {
+        if self.state.hibernating {
+            self.reset().await?;
+        }
+
+        self.send(command).await?;
+        self.config
+            .spi
+            .transaction(&mut [Operation::Read(buf)])
+            .await?;
+
+        Ok(())
+    }
+
     #[cfg_attr(not(feature = "async"), remove_async_await::remove_async_await)]
     async fn transfer_command(&mut self, value: u8) -> Result<(), Error<C>> {
         do_output(self.config.dc.set_low())?;
@@ -604,6 +1429,68 @@ fn do_output<T, Spi, Input, Output>(
     }
 }
 
+/// Returns whether the busy wait that produced `r` finished, turning a timeout into `Ok(false)`.
+fn settled<Spi, Input, Output>(
+    r: Result<(), DisplayError<Spi, Input, Output>>,
+) -> Result<bool, DisplayError<Spi, Input, Output>> {
+    match r {
+        Ok(()) => Ok(true),
+        Err(DisplayError::BusyTimeout) => Ok(false),
+        Err(e) => Err(e),
+    }
+}
+
+/// Returns the stride of a bitmap whose rows are packed without gaps, checking that it is exactly
+/// the right size for the area.
+fn packed_stride<Spi, Input, Output>(
+    bitmap: &[u8],
+    x_lo: i16,
+    y_lo: i16,
+    x_hi: i16,
+    y_hi: i16,
+) -> Result<usize, DisplayError<Spi, Input, Output>> {
+    let stride = (x_hi - x_lo).max(0) as usize / 8 + ((x_hi - x_lo).max(0) % 8 != 0) as usize;
+    if bitmap.len() != stride * (y_hi - y_lo).max(0) as usize {
+        return Err(DisplayError::InvalidBitmap);
+    }
+
+    Ok(stride)
+}
+
+/// Checks that `bitmap` holds every row of `rect`, with rows `stride` bytes apart.
+fn bitmap_fits(bitmap: &[u8], stride: usize, rect: Rect) -> bool {
+    if rect.x.size() <= 0 || rect.y.size() <= 0 {
+        return true;
+    }
+    let row_len = ceil_multiple(rect.x.size(), 8) as usize / 8;
+
+    stride >= row_len && bitmap.len() >= stride * (rect.y.size() as usize - 1) + row_len
+}
+
+/// Reads the 8 pixels starting `offset` pixels into `row`, with the first `lead` and the last
+/// `trail` of them set to white.
+fn clipped_byte(row: &[u8], offset: i16, lead: i16, trail: i16) -> u8 {
+    let byte_at = |i: i16| match i {
+        0.. => row.get(i as usize).copied().unwrap_or(0xFF),
+        _ => 0xFF,
+    };
+    let index = offset.div_euclid(8);
+    let shift = offset.rem_euclid(8) as u32;
+
+    let mut byte = match shift {
+        0 => byte_at(index),
+        _ => (byte_at(index) << shift) | (byte_at(index + 1) >> (8 - shift)),
+    };
+    if lead > 0 {
+        byte |= !(0xFF >> lead as u32);
+    }
+    if trail > 0 {
+        byte |= (1 << trail as u32) - 1;
+    }
+
+    byte
+}
+
 fn floor_multiple(n: i16, m: i16) -> i16 {
     n - n % m
 }
@@ -0,0 +1,134 @@
+use crate::geometry::{Rect, Span};
+
+/// Describes a panel driven by an SSD1680 or SSD1681 controller: its size, and the register
+/// values it needs that differ between panels.
+pub trait Panel {
+    /// The width of the visible area in pixels.
+    const WIDTH: u16;
+    /// The height of the visible area in pixels.
+    const HEIGHT: u16;
+
+    /// A bitmap covering the whole screen, as used by framebuffers. Each row starts on a new
+    /// byte, so this must be `(WIDTH + 7) / 8 * HEIGHT` bytes long.
+    type Buffer: AsRef<[u8]> + AsMut<[u8]>;
+    /// A white [Panel::Buffer].
+    const WHITE: Self::Buffer;
+
+    /// The parameters of the driver output control command (0x01): the number of gate lines
+    /// minus one, little-endian, and the gate scanning order.
+    const DRIVER_OUTPUT_CONTROL: [u8; 3] = [
+        ((Self::HEIGHT - 1) % 256) as u8,
+        ((Self::HEIGHT - 1) / 256) as u8,
+        0x00,
+    ];
+    /// The source output mode sent with display update control 1 (0x21), which some panels need
+    /// to address the right sources. 0x00 is the controller's default and is not sent.
+    const SOURCE_OUTPUT_MODE: u8 = 0x00;
+    /// The border waveform control (0x3C) values for a white and a dark border.
+    const BORDER_WHITE: u8 = 0x05;
+    const BORDER_DARK: u8 = 0x02;
+
+    /// The display update control 2 (0x22) values for turning the panel on and off, and for full
+    /// and partial refreshes using the waveforms in OTP.
+    const POWER_ON: u8 = 0xf8;
+    const POWER_OFF: u8 = 0x83;
+    const UPDATE_FULL: u8 = 0xf4;
+    const UPDATE_PARTIAL: u8 = 0xfc;
+
+    /// What the panel can do.
+    const FEATURES: Features = Features {
+        partial_refresh: true,
+        tri_color: false,
+    };
+}
+
+/// The capabilities of a [Panel].
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Features {
+    /// Whether the panel supports partial refreshes. Without them, every refresh is full.
+    pub partial_refresh: bool,
+    /// Whether the panel can show red as well as black and white. On these panels, the RAM that
+    /// other panels use for the previous image (0x26) holds the red plane instead.
+    pub tri_color: bool,
+}
+
+/// The Good Display GDEH0154D67, a 1.54" 200x200 panel with an SSD1681. This is the panel the
+/// Watchy uses.
+#[derive(Clone, Copy, Debug, Default)]
+pub struct Gdeh0154d67;
+
+impl Panel for Gdeh0154d67 {
+    const WIDTH: u16 = 200;
+    const HEIGHT: u16 = 200;
+
+    type Buffer = [u8; 200 / 8 * 200];
+    const WHITE: Self::Buffer = [0xFF; 200 / 8 * 200];
+}
+
+/// The Good Display GDEH0154Z90, a 1.54" 200x200 black, white and red panel with an SSD1681.
+#[derive(Clone, Copy, Debug, Default)]
+pub struct Gdeh0154z90;
+
+impl Panel for Gdeh0154z90 {
+    const WIDTH: u16 = 200;
+    const HEIGHT: u16 = 200;
+
+    type Buffer = [u8; 200 / 8 * 200];
+    const WHITE: Self::Buffer = [0xFF; 200 / 8 * 200];
+
+    const UPDATE_FULL: u8 = 0xf7;
+
+    const FEATURES: Features = Features {
+        partial_refresh: false,
+        tri_color: true,
+    };
+}
+
+/// The Good Display GDEY0213B74, a 2.13" 122x250 panel with an SSD1680.
+#[derive(Clone, Copy, Debug, Default)]
+pub struct Gdey0213b74;
+
+impl Panel for Gdey0213b74 {
+    const WIDTH: u16 = 122;
+    const HEIGHT: u16 = 250;
+
+    type Buffer = [u8; 128 / 8 * 250];
+    const WHITE: Self::Buffer = [0xFF; 128 / 8 * 250];
+
+    const SOURCE_OUTPUT_MODE: u8 = 0x80;
+    const POWER_ON: u8 = 0xe0;
+}
+
+/// The Good Display GDEY029T94, a 2.9" 128x296 panel with an SSD1680.
+#[derive(Clone, Copy, Debug, Default)]
+pub struct Gdey029t94;
+
+impl Panel for Gdey029t94 {
+    const WIDTH: u16 = 128;
+    const HEIGHT: u16 = 296;
+
+    type Buffer = [u8; 128 / 8 * 296];
+    const WHITE: Self::Buffer = [0xFF; 128 / 8 * 296];
+
+    const SOURCE_OUTPUT_MODE: u8 = 0x80;
+    const POWER_ON: u8 = 0xe0;
+}
+
+/// The number of bytes in each row of a full-screen bitmap for `P`.
+pub(crate) const fn stride<P: Panel>() -> usize {
+    (P::WIDTH as usize).div_ceil(8)
+}
+
+/// The whole screen of `P`.
+pub(crate) const fn screen_rect<P: Panel>() -> Rect {
+    Rect {
+        x: Span {
+            lo: 0,
+            hi: P::WIDTH as i16,
+        },
+        y: Span {
+            lo: 0,
+            hi: P::HEIGHT as i16,
+        },
+    }
+}
@@ -0,0 +1,204 @@
+//! Wrappers that record what the driver does on the bus, for protocol-conformance tests.
+//!
+//! The wrappers forward everything to the device or pin they wrap, so they can sit in front of
+//! real hardware or a [crate::sim::Simulator] alike. The data/command pin wrapper tells the SPI
+//! wrapper whether a byte is a command or data.
+
+use std::{cell::RefCell, rc::Rc, vec::Vec};
+
+use embedded_hal::{
+    digital::{ErrorType as DigitalErrorType, OutputPin},
+    spi::{ErrorType as SpiErrorType, Operation},
+};
+
+/// Something the driver did, as seen from the bus.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum Event {
+    /// A byte written with the data/command pin low.
+    Command(u8),
+    /// Bytes written with the data/command pin high. Consecutive writes are merged.
+    Data(Vec<u8>),
+    /// Bytes read back. Consecutive reads are merged.
+    Read(usize),
+    /// The reset pin was driven to the given level.
+    Reset(bool),
+}
+
+#[derive(Default)]
+struct Log {
+    events: Vec<Event>,
+    transactions: usize,
+    dc_high: bool,
+}
+
+impl Log {
+    fn write(&mut self, bytes: &[u8]) {
+        if !self.dc_high {
+            self.events.extend(bytes.iter().map(|&b| Event::Command(b)));
+            return;
+        }
+        match self.events.last_mut() {
+            Some(Event::Data(data)) => data.extend_from_slice(bytes),
+            _ => self.events.push(Event::Data(bytes.into())),
+        }
+    }
+
+    fn read(&mut self, len: usize) {
+        match self.events.last_mut() {
+            Some(Event::Read(n)) => *n += len,
+            _ => self.events.push(Event::Read(len)),
+        }
+    }
+}
+
+/// Hands out the recording wrappers and collects what they see.
+#[derive(Clone, Default)]
+pub struct Recorder {
+    log: Rc<RefCell<Log>>,
+}
+
+impl Recorder {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn spi<S>(&self, spi: S) -> RecordingSpi<S> {
+        RecordingSpi {
+            inner: spi,
+            log: self.log.clone(),
+        }
+    }
+
+    /// Wraps the data/command pin. This must be used for the SPI bytes to be told apart.
+    pub fn dc<P>(&self, pin: P) -> RecordingDc<P> {
+        RecordingDc {
+            inner: pin,
+            log: self.log.clone(),
+        }
+    }
+
+    pub fn rst<P>(&self, pin: P) -> RecordingRst<P> {
+        RecordingRst {
+            inner: pin,
+            log: self.log.clone(),
+        }
+    }
+
+    /// Returns the events recorded so far.
+    pub fn events(&self) -> Vec<Event> {
+        self.log.borrow().events.clone()
+    }
+
+    /// Returns the events recorded so far and clears the log.
+    pub fn take(&self) -> Vec<Event> {
+        let mut log = self.log.borrow_mut();
+        log.transactions = 0;
+        core::mem::take(&mut log.events)
+    }
+
+    /// Returns the number of SPI transactions recorded so far.
+    pub fn transactions(&self) -> usize {
+        self.log.borrow().transactions
+    }
+
+    /// Returns just the command bytes recorded so far.
+    pub fn commands(&self) -> Vec<u8> {
+        self.log
+            .borrow()
+            .events
+            .iter()
+            .filter_map(|event| match event {
+                Event::Command(command) => Some(*command),
+                _ => None,
+            })
+            .collect()
+    }
+}
+
+pub struct RecordingSpi<S> {
+    inner: S,
+    log: Rc<RefCell<Log>>,
+}
+
+impl<S> RecordingSpi<S> {
+    fn record(&self, operations: &[Operation<'_, u8>]) {
+        let mut log = self.log.borrow_mut();
+        log.transactions += 1;
+        for operation in operations {
+            match operation {
+                Operation::Write(buf) => log.write(buf),
+                Operation::Read(buf) => log.read(buf.len()),
+                Operation::Transfer(read, write) => {
+                    log.write(write);
+                    log.read(read.len());
+                }
+                Operation::TransferInPlace(buf) => {
+                    log.write(buf);
+                    log.read(buf.len());
+                }
+                Operation::DelayNs(_) => (),
+            }
+        }
+    }
+}
+
+impl<S: SpiErrorType> SpiErrorType for RecordingSpi<S> {
+    type Error = S::Error;
+}
+
+impl<S: embedded_hal::spi::SpiDevice> embedded_hal::spi::SpiDevice for RecordingSpi<S> {
+    fn transaction(&mut self, operations: &mut [Operation<'_, u8>]) -> Result<(), S::Error> {
+        self.record(operations);
+        self.inner.transaction(operations)
+    }
+}
+
+#[cfg(feature = "async")]
+impl<S: embedded_hal_async::spi::SpiDevice> embedded_hal_async::spi::SpiDevice for RecordingSpi<S> {
+    async fn transaction(&mut self, operations: &mut [Operation<'_, u8>]) -> Result<(), S::Error> {
+        self.record(operations);
+        self.inner.transaction(operations).await
+    }
+}
+
+pub struct RecordingDc<P> {
+    inner: P,
+    log: Rc<RefCell<Log>>,
+}
+
+impl<P: DigitalErrorType> DigitalErrorType for RecordingDc<P> {
+    type Error = P::Error;
+}
+
+impl<P: OutputPin> OutputPin for RecordingDc<P> {
+    fn set_low(&mut self) -> Result<(), P::Error> {
+        self.log.borrow_mut().dc_high = false;
+        self.inner.set_low()
+    }
+
+    fn set_high(&mut self) -> Result<(), P::Error> {
+        self.log.borrow_mut().dc_high = true;
+        self.inner.set_high()
+    }
+}
+
+pub struct RecordingRst<P> {
+    inner: P,
+    log: Rc<RefCell<Log>>,
+}
+
+impl<P: DigitalErrorType> DigitalErrorType for RecordingRst<P> {
+    type Error = P::Error;
+}
+
+impl<P: OutputPin> OutputPin for RecordingRst<P> {
+    fn set_low(&mut self) -> Result<(), P::Error> {
+        self.log.borrow_mut().events.push(Event::Reset(false));
+        self.inner.set_low()
+    }
+
+    fn set_high(&mut self) -> Result<(), P::Error> {
+        self.log.borrow_mut().events.push(Event::Reset(true));
+        self.inner.set_high()
+    }
+}
@@ -0,0 +1,622 @@
+//! A simulated panel for testing without hardware.
+//!
+//! [Simulator] decodes the commands the driver sends into the controller's RAM and registers, and
+//! keeps an image of what the panel would show, updated whenever a display update is activated.
+//!
+//! ```ignore
+//! let sim = Simulator::new(200, 200);
+//! let mut display = Display::new(sim.config()).unwrap();
+//! display.clear_screen(0x00).unwrap();
+//! assert!(!sim.pixel(10, 10));
+//! ```
+
+use core::convert::Infallible;
+use std::{cell::RefCell, collections::BTreeMap, rc::Rc, vec, vec::Vec};
+
+use embedded_hal::{
+    digital::{ErrorType as DigitalErrorType, InputPin, OutputPin},
+    spi::{ErrorType as SpiErrorType, Operation},
+};
+
+use crate::{DelayWaiter, DisplayConfiguration};
+
+pub type SimConfiguration =
+    DisplayConfiguration<SimSpi, SimDc, SimRst, SimBusy, SimDelay, DelayWaiter<SimDelay>>;
+
+/// A simulated SSD1681 controller and the panel attached to it.
+#[derive(Clone)]
+pub struct Simulator {
+    controller: Rc<RefCell<Controller>>,
+}
+
+impl Simulator {
+    /// Creates a simulated panel of the given size, with white RAM and a white image.
+    pub fn new(width: u16, height: u16) -> Self {
+        Self {
+            controller: Rc::new(RefCell::new(Controller::new(width, height))),
+        }
+    }
+
+    /// Creates a simulated black, white and red panel, which shows the red RAM as a red plane
+    /// instead of using it for the previous image.
+    pub fn tri_color(width: u16, height: u16) -> Self {
+        let sim = Self::new(width, height);
+        sim.controller.borrow_mut().tri_color = true;
+        sim
+    }
+
+    pub fn spi(&self) -> SimSpi {
+        SimSpi {
+            controller: self.controller.clone(),
+        }
+    }
+
+    pub fn dc(&self) -> SimDc {
+        SimDc {
+            controller: self.controller.clone(),
+        }
+    }
+
+    pub fn rst(&self) -> SimRst {
+        SimRst {
+            controller: self.controller.clone(),
+        }
+    }
+
+    pub fn busy(&self) -> SimBusy {
+        SimBusy {
+            controller: self.controller.clone(),
+        }
+    }
+
+    /// Creates a configuration for a [crate::Display] driving this panel.
+    pub fn config(&self) -> SimConfiguration {
+        DisplayConfiguration {
+            spi: self.spi(),
+            dc: self.dc(),
+            rst: self.rst(),
+            busy: self.busy(),
+            delay: SimDelay,
+            busy_wait: DelayWaiter::new(SimDelay),
+        }
+    }
+
+    pub fn width(&self) -> u16 {
+        self.controller.borrow().width
+    }
+
+    pub fn height(&self) -> u16 {
+        self.controller.borrow().height
+    }
+
+    /// Returns whether the panel shows the pixel at `(x, y)` as white.
+    pub fn pixel(&self, x: u16, y: u16) -> bool {
+        let controller = self.controller.borrow();
+        bit(&controller.visible, controller.stride(), x, y)
+    }
+
+    /// Returns whether a tri-color panel shows the pixel at `(x, y)` as red.
+    pub fn red_pixel(&self, x: u16, y: u16) -> bool {
+        let controller = self.controller.borrow();
+        bit(&controller.visible_red, controller.stride(), x, y)
+    }
+
+    /// Returns the image the panel shows, one bit per pixel with 1 being white, and each row
+    /// starting on a new byte.
+    pub fn visible(&self) -> Vec<u8> {
+        self.controller.borrow().visible.clone()
+    }
+
+    /// Returns the contents of the black and white RAM (written with 0x24).
+    pub fn bw_ram(&self) -> Vec<u8> {
+        self.controller.borrow().bw.clone()
+    }
+
+    /// Returns the contents of the red RAM (written with 0x26).
+    pub fn red_ram(&self) -> Vec<u8> {
+        self.controller.borrow().red.clone()
+    }
+
+    /// Returns the data last written along with `command`, if it was ever sent. RAM writes are
+    /// not recorded here.
+    pub fn register(&self, command: u8) -> Option<Vec<u8>> {
+        self.controller.borrow().registers.get(&command).cloned()
+    }
+
+    /// Returns the display update control value (0x22) used by each activation so far.
+    pub fn updates(&self) -> Vec<u8> {
+        self.controller.borrow().updates.clone()
+    }
+
+    /// Returns whether the controller is in deep sleep.
+    pub fn is_sleeping(&self) -> bool {
+        self.controller.borrow().sleeping
+    }
+
+    /// Sets the user ID read back with 0x2E, which real panels have programmed into OTP.
+    pub fn set_user_id(&self, user_id: [u8; 10]) {
+        self.controller.borrow_mut().user_id = user_id;
+    }
+
+    /// Sets the OTP display options read back with 0x2D.
+    pub fn set_display_option(&self, display_option: [u8; 11]) {
+        self.controller.borrow_mut().display_option = display_option;
+    }
+
+    /// Sets whether the booster's voltages come up, as found by HV ready detection (0x14).
+    pub fn set_hv_ready(&self, ready: bool) {
+        self.controller.borrow_mut().hv_ready = ready;
+    }
+
+    /// Sets whether the supply voltage is above the level checked by VCI detection (0x15).
+    pub fn set_vci_ok(&self, ok: bool) {
+        self.controller.borrow_mut().vci_ok = ok;
+    }
+
+    /// Connects or disconnects the panel. A disconnected panel ignores everything sent to it, and
+    /// reads return 0xFF as if the data line were pulled up.
+    pub fn set_connected(&self, connected: bool) {
+        self.controller.borrow_mut().connected = connected;
+    }
+
+    /// Sets the temperature reported by the internal sensor, in units of 1/16 °C.
+    pub fn set_temperature(&self, sixteenths: i16) {
+        self.controller.borrow_mut().sensor = sixteenths;
+    }
+}
+
+struct Controller {
+    width: u16,
+    height: u16,
+    tri_color: bool,
+    connected: bool,
+    data: bool,
+    in_reset: bool,
+    sleeping: bool,
+    busy: bool,
+    command: Option<u8>,
+    params: Vec<u8>,
+    registers: BTreeMap<u8, Vec<u8>>,
+    bw: Vec<u8>,
+    red: Vec<u8>,
+    visible: Vec<u8>,
+    visible_red: Vec<u8>,
+    data_entry_mode: u8,
+    x_window: (u16, u16),
+    y_window: (u16, u16),
+    x_counter: u16,
+    y_counter: u16,
+    sensor: i16,
+    temperature: i16,
+    user_id: [u8; 10],
+    display_option: [u8; 11],
+    hv_ready: bool,
+    vci_ok: bool,
+    status: u8,
+    read_position: usize,
+    updates: Vec<u8>,
+}
+
+impl Controller {
+    fn new(width: u16, height: u16) -> Self {
+        let size = width.div_ceil(8) as usize * height as usize;
+        let mut controller = Self {
+            width,
+            height,
+            tri_color: false,
+            connected: true,
+            data: true,
+            in_reset: false,
+            sleeping: false,
+            busy: false,
+            command: None,
+            params: Vec::new(),
+            registers: BTreeMap::new(),
+            bw: vec![0xFF; size],
+            red: vec![0xFF; size],
+            visible: vec![0xFF; size],
+            visible_red: vec![0x00; size],
+            data_entry_mode: 0,
+            x_window: (0, 0),
+            y_window: (0, 0),
+            x_counter: 0,
+            y_counter: 0,
+            sensor: 25 * 16,
+            temperature: 0,
+            user_id: [0; 10],
+            display_option: [0; 11],
+            hv_ready: true,
+            vci_ok: true,
+            status: 0,
+            read_position: 0,
+            updates: Vec::new(),
+        };
+        controller.reset();
+        controller
+    }
+
+    fn stride(&self) -> usize {
+        self.width.div_ceil(8) as usize
+    }
+
+    /// Puts the registers back to their power-on values. The RAM is left alone.
+    fn reset(&mut self) {
+        self.sleeping = false;
+        self.command = None;
+        self.params.clear();
+        self.registers.clear();
+        self.data_entry_mode = 0x03;
+        self.x_window = (0, self.width.div_ceil(8) - 1);
+        self.y_window = (0, self.height - 1);
+        self.x_counter = 0;
+        self.y_counter = 0;
+        self.status = 0x01;
+    }
+
+    fn write(&mut self, byte: u8) {
+        if !self.connected || self.in_reset || self.sleeping {
+            return;
+        }
+
+        if !self.data {
+            self.command = Some(byte);
+            self.params.clear();
+            self.read_position = 0;
+            self.registers.entry(byte).or_default().clear();
+            if byte == 0x20 {
+                self.activate();
+            }
+            return;
+        }
+
+        let Some(command) = self.command else {
+            return;
+        };
+        match command {
+            0x24 | 0x26 => self.write_ram(command, byte),
+            _ => {
+                self.params.push(byte);
+                self.registers.insert(command, self.params.clone());
+                self.apply(command);
+            }
+        }
+    }
+
+    fn read(&mut self) -> u8 {
+        if !self.connected || self.in_reset || self.sleeping {
+            return 0xFF;
+        }
+
+        let position = self.read_position;
+        self.read_position += 1;
+        match self.command {
+            Some(0x1b) => {
+                let register = [
+                    (self.temperature >> 4) as u8,
+                    ((self.temperature & 0xF) << 4) as u8,
+                ];
+                register.get(position).copied().unwrap_or(0)
+            }
+            // The first byte read from RAM is a dummy
+            Some(0x27) if position == 0 => 0,
+            Some(0x27) => self.read_ram(),
+            Some(0x2d) => self.display_option.get(position).copied().unwrap_or(0),
+            Some(0x2e) => self.user_id.get(position).copied().unwrap_or(0),
+            Some(0x2f) => self.status,
+            _ => 0,
+        }
+    }
+
+    fn read_ram(&mut self) -> u8 {
+        let stride = self.stride();
+        let (x, y) = (self.x_counter as usize, self.y_counter as usize);
+        let red = self
+            .registers
+            .get(&0x41)
+            .and_then(|params| params.first())
+            .is_some_and(|option| option & 0x01 != 0);
+        let ram = if red { &self.red } else { &self.bw };
+        let byte = match x < stride && y < self.height as usize {
+            true => ram[y * stride + x],
+            false => 0,
+        };
+
+        self.advance();
+        byte
+    }
+
+    /// Handles the parameters of `command` received so far.
+    fn apply(&mut self, command: u8) {
+        let params = &self.params;
+        match (command, params.len()) {
+            (0x10, 1) => self.sleeping = params[0] & 0x03 != 0,
+            (0x11, 1) => self.data_entry_mode = params[0] & 0x07,
+            (0x14, 1) => {
+                set_bit(&mut self.status, 0x20, !self.hv_ready);
+                self.busy = true;
+            }
+            (0x15, 1) => {
+                set_bit(&mut self.status, 0x10, !self.vci_ok);
+                self.busy = true;
+            }
+            (0x1a, 2) => {
+                self.temperature = ((params[0] as i8 as i16) << 4) | (params[1] >> 4) as i16;
+            }
+            (0x44, 2) => self.x_window = (x_address(params[0]), x_address(params[1])),
+            (0x45, 4) => {
+                self.y_window = (
+                    y_address(params[0], params[1]),
+                    y_address(params[2], params[3]),
+                );
+            }
+            (0x4e, 1) => self.x_counter = x_address(params[0]),
+            (0x4f, 2) => self.y_counter = y_address(params[0], params[1]),
+            _ => (),
+        }
+    }
+
+    fn write_ram(&mut self, command: u8, byte: u8) {
+        let stride = self.stride();
+        let (x, y) = (self.x_counter as usize, self.y_counter as usize);
+        let ram = match command {
+            0x24 => &mut self.bw,
+            _ => &mut self.red,
+        };
+        if x < stride && y < self.height as usize {
+            ram[y * stride + x] = byte;
+        }
+
+        self.advance();
+    }
+
+    /// Moves the address counters on to the next byte of RAM.
+    fn advance(&mut self) {
+        let x_increment = self.data_entry_mode & 0x01 != 0;
+        let y_increment = self.data_entry_mode & 0x02 != 0;
+        let y_first = self.data_entry_mode & 0x04 != 0;
+        if y_first {
+            if step(&mut self.y_counter, self.y_window, y_increment) {
+                step(&mut self.x_counter, self.x_window, x_increment);
+            }
+        } else if step(&mut self.x_counter, self.x_window, x_increment) {
+            step(&mut self.y_counter, self.y_window, y_increment);
+        }
+    }
+
+    /// Runs the display update sequence selected with 0x22.
+    fn activate(&mut self) {
+        let control = self
+            .registers
+            .get(&0x22)
+            .and_then(|params| params.first().copied())
+            .unwrap_or(0xff);
+        self.updates.push(control);
+        self.busy = true;
+
+        if control & 0x20 != 0 {
+            self.temperature = self.sensor;
+        }
+        let options = self
+            .registers
+            .get(&0x21)
+            .and_then(|params| params.first().copied())
+            .unwrap_or(0x00);
+        if control & 0x04 != 0 && self.tri_color {
+            shown(&mut self.visible, &self.bw, options & 0x0F);
+            shown(&mut self.visible_red, &self.red, options >> 4);
+        } else if control & 0x04 != 0 {
+            shown(&mut self.visible, &self.bw, options & 0x0F);
+            self.red.copy_from_slice(&self.bw);
+        }
+    }
+}
+
+/// Copies `ram` into `visible` as the display update control 1 (0x21) `option` has it read.
+fn shown(visible: &mut [u8], ram: &[u8], option: u8) {
+    for (pixels, &byte) in visible.iter_mut().zip(ram) {
+        *pixels = match option {
+            0x4 => 0x00,
+            0x8 => !byte,
+            _ => byte,
+        };
+    }
+}
+
+/// Decodes an X address, which is 6 bits wide.
+fn x_address(byte: u8) -> u16 {
+    (byte & 0x3F) as u16
+}
+
+/// Decodes a Y address, which is 9 bits wide and sent low byte first.
+fn y_address(lo: u8, hi: u8) -> u16 {
+    u16::from_le_bytes([lo, hi & 0x01])
+}
+
+/// Moves an address counter one step through its window, wrapping around at the end. Returns
+/// whether it wrapped.
+fn step(counter: &mut u16, window: (u16, u16), increment: bool) -> bool {
+    let (start, end) = window;
+    if *counter == end {
+        *counter = start;
+        true
+    } else {
+        if increment {
+            *counter = counter.wrapping_add(1);
+        } else {
+            *counter = counter.wrapping_sub(1);
+        }
+        false
+    }
+}
+
+fn set_bit(byte: &mut u8, mask: u8, set: bool) {
+    if set {
+        *byte |= mask;
+    } else {
+        *byte &= !mask;
+    }
+}
+
+fn bit(buffer: &[u8], stride: usize, x: u16, y: u16) -> bool {
+    buffer[y as usize * stride + x as usize / 8] & (0x80 >> (x % 8)) != 0
+}
+
+pub struct SimSpi {
+    controller: Rc<RefCell<Controller>>,
+}
+
+impl SimSpi {
+    fn run(&mut self, operations: &mut [Operation<'_, u8>]) {
+        let mut controller = self.controller.borrow_mut();
+        for operation in operations {
+            match operation {
+                Operation::Read(buf) => buf.iter_mut().for_each(|b| *b = controller.read()),
+                Operation::Write(buf) => buf.iter().for_each(|&b| controller.write(b)),
+                Operation::Transfer(read, write) => {
+                    write.iter().for_each(|&b| controller.write(b));
+                    read.iter_mut().for_each(|b| *b = controller.read());
+                }
+                Operation::TransferInPlace(buf) => buf.iter_mut().for_each(|b| {
+                    controller.write(*b);
+                    *b = controller.read();
+                }),
+                Operation::DelayNs(_) => (),
+            }
+        }
+    }
+}
+
+impl SpiErrorType for SimSpi {
+    type Error = Infallible;
+}
+
+impl embedded_hal::spi::SpiDevice for SimSpi {
+    fn transaction(&mut self, operations: &mut [Operation<'_, u8>]) -> Result<(), Infallible> {
+        self.run(operations);
+        Ok(())
+    }
+}
+
+#[cfg(feature = "async")]
+impl embedded_hal_async::spi::SpiDevice for SimSpi {
+    async fn transaction(
+        &mut self,
+        operations: &mut [Operation<'_, u8>],
+    ) -> Result<(), Infallible> {
+        self.run(operations);
+        Ok(())
+    }
+}
+
+/// The simulated data/command pin.
+pub struct SimDc {
+    controller: Rc<RefCell<Controller>>,
+}
+
+impl DigitalErrorType for SimDc {
+    type Error = Infallible;
+}
+
+impl OutputPin for SimDc {
+    fn set_low(&mut self) -> Result<(), Infallible> {
+        self.controller.borrow_mut().data = false;
+        Ok(())
+    }
+
+    fn set_high(&mut self) -> Result<(), Infallible> {
+        self.controller.borrow_mut().data = true;
+        Ok(())
+    }
+}
+
+/// The simulated reset pin. Releasing it resets the controller's registers.
+pub struct SimRst {
+    controller: Rc<RefCell<Controller>>,
+}
+
+impl DigitalErrorType for SimRst {
+    type Error = Infallible;
+}
+
+impl OutputPin for SimRst {
+    fn set_low(&mut self) -> Result<(), Infallible> {
+        self.controller.borrow_mut().in_reset = true;
+        Ok(())
+    }
+
+    fn set_high(&mut self) -> Result<(), Infallible> {
+        let mut controller = self.controller.borrow_mut();
+        if controller.in_reset {
+            controller.in_reset = false;
+            controller.reset();
+        }
+        Ok(())
+    }
+}
+
+/// The simulated busy pin. It goes high when the controller starts working on a display update
+/// or a detection, and since the simulated controller finishes everything instantly, it goes low
+/// again as soon as it has been seen high once.
+pub struct SimBusy {
+    controller: Rc<RefCell<Controller>>,
+}
+
+impl SimBusy {
+    fn take(&mut self) -> bool {
+        core::mem::take(&mut self.controller.borrow_mut().busy)
+    }
+}
+
+impl DigitalErrorType for SimBusy {
+    type Error = Infallible;
+}
+
+impl InputPin for SimBusy {
+    fn is_high(&mut self) -> Result<bool, Infallible> {
+        Ok(self.take())
+    }
+
+    fn is_low(&mut self) -> Result<bool, Infallible> {
+        Ok(!self.take())
+    }
+}
+
+#[cfg(feature = "async")]
+impl embedded_hal_async::digital::Wait for SimBusy {
+    async fn wait_for_high(&mut self) -> Result<(), Infallible> {
+        match self.take() {
+            true => Ok(()),
+            false => core::future::pending().await,
+        }
+    }
+
+    async fn wait_for_low(&mut self) -> Result<(), Infallible> {
+        self.take();
+        Ok(())
+    }
+
+    async fn wait_for_rising_edge(&mut self) -> Result<(), Infallible> {
+        Ok(())
+    }
+
+    async fn wait_for_falling_edge(&mut self) -> Result<(), Infallible> {
+        Ok(())
+    }
+
+    async fn wait_for_any_edge(&mut self) -> Result<(), Infallible> {
+        Ok(())
+    }
+}
+
+/// A delay that returns immediately.
+#[derive(Clone, Copy)]
+pub struct SimDelay;
+
+impl embedded_hal::delay::DelayNs for SimDelay {
+    fn delay_ns(&mut self, _ns: u32) {}
+}
+
+#[cfg(feature = "async")]
+impl embedded_hal_async::delay::DelayNs for SimDelay {
+    async fn delay_ns(&mut self, _ns: u32) {}
+}
@@ -0,0 +1,297 @@
+//! Saving and comparing images of the screen, for golden-image tests.
+//!
+//! A [Snapshot] holds a 1-bit image in the same layout the driver uses everywhere else: one bit
+//! per pixel with 1 being white, and each row starting on a new byte. It can be taken from a
+//! [crate::Framebuffer] or a [crate::sim::Simulator], and read from and written to PBM files.
+
+use std::{
+    eprintln,
+    fmt::Write as _,
+    format, fs,
+    io::{self, BufRead, Read, Write},
+    path::{Path, PathBuf},
+    string::String,
+    vec,
+    vec::Vec,
+};
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Snapshot {
+    width: u16,
+    height: u16,
+    data: Vec<u8>,
+}
+
+impl Snapshot {
+    /// Creates a snapshot from a bitmap, which must be exactly `(width + 7) / 8 * height` bytes.
+    pub fn new(width: u16, height: u16, data: Vec<u8>) -> Self {
+        assert_eq!(
+            data.len(),
+            width.div_ceil(8) as usize * height as usize,
+            "bitmap size does not match {width}x{height}"
+        );
+        Self {
+            width,
+            height,
+            data,
+        }
+    }
+
+    pub fn width(&self) -> u16 {
+        self.width
+    }
+
+    pub fn height(&self) -> u16 {
+        self.height
+    }
+
+    pub fn data(&self) -> &[u8] {
+        &self.data
+    }
+
+    /// Returns whether the pixel at `(x, y)` is white.
+    pub fn pixel(&self, x: u16, y: u16) -> bool {
+        self.data[y as usize * self.stride() + x as usize / 8] & (0x80 >> (x % 8)) != 0
+    }
+
+    fn stride(&self) -> usize {
+        self.width.div_ceil(8) as usize
+    }
+
+    /// Writes the snapshot as a binary PBM (P4) image.
+    pub fn write_pbm(&self, mut writer: impl Write) -> io::Result<()> {
+        write!(writer, "P4\n{} {}\n", self.width, self.height)?;
+        // PBM uses 1 for black
+        let inverted: Vec<u8> = self.data.iter().map(|b| !b).collect();
+        writer.write_all(&inverted)
+    }
+
+    /// Reads a binary PBM (P4) image.
+    pub fn read_pbm(reader: impl Read) -> io::Result<Self> {
+        let mut reader = io::BufReader::new(reader);
+        let mut header = Vec::new();
+        while header.len() < 3 {
+            let mut line = String::new();
+            if reader.read_line(&mut line)? == 0 {
+                return Err(invalid_data("truncated PBM header"));
+            }
+            let line = line.split('#').next().unwrap_or_default();
+            header.extend(line.split_whitespace().map(String::from));
+        }
+
+        if header[0] != "P4" {
+            return Err(invalid_data("not a binary PBM image"));
+        }
+        let parse = |s: &str| s.parse::<u16>().map_err(|_| invalid_data("bad PBM size"));
+        let width = parse(&header[1])?;
+        let height = parse(&header[2])?;
+
+        let mut data = vec![0; width.div_ceil(8) as usize * height as usize];
+        reader.read_exact(&mut data)?;
+        data.iter_mut().for_each(|b| *b = !*b);
+
+        Ok(Self {
+            width,
+            height,
+            data,
+        })
+    }
+
+    /// Writes the snapshot as a 1-bit grayscale PNG image.
+    #[cfg(feature = "png")]
+    pub fn write_png(&self, mut writer: impl Write) -> io::Result<()> {
+        let mut raw = Vec::with_capacity((self.stride() + 1) * self.height as usize);
+        for row in self.data.chunks(self.stride()) {
+            raw.push(0);
+            raw.extend_from_slice(row);
+        }
+
+        let mut ihdr = Vec::new();
+        ihdr.extend_from_slice(&(self.width as u32).to_be_bytes());
+        ihdr.extend_from_slice(&(self.height as u32).to_be_bytes());
+        ihdr.extend_from_slice(&[1, 0, 0, 0, 0]);
+
+        writer.write_all(b"\x89PNG\r\n\x1a\n")?;
+        png::write_chunk(&mut writer, b"IHDR", &ihdr)?;
+        png::write_chunk(&mut writer, b"IDAT", &png::zlib_stored(&raw))?;
+        png::write_chunk(&mut writer, b"IEND", &[])
+    }
+
+    /// Returns an image of the pixels that differ from `other` in black, or `None` if they are
+    /// the same. Snapshots of different sizes differ everywhere.
+    pub fn diff(&self, other: &Snapshot) -> Option<Snapshot> {
+        if self.width != other.width || self.height != other.height {
+            return Some(Self::new(self.width, self.height, vec![0; self.data.len()]));
+        }
+
+        let data = self
+            .data
+            .iter()
+            .zip(&other.data)
+            .map(|(a, b)| !(a ^ b))
+            .collect();
+        let diff = Self::new(self.width, self.height, data);
+        match diff.count_black() {
+            0 => None,
+            _ => Some(diff),
+        }
+    }
+
+    /// Compares the snapshot against the PBM image at `path`, and panics if they differ.
+    ///
+    /// On a mismatch, the actual image and a diff image are written next to the golden one, as
+    /// `<name>.actual.pbm` and `<name>.diff.pbm`, and the area that differs is printed. Setting
+    /// the `WEPD_BLESS` environment variable writes the snapshot to `path` instead.
+    pub fn assert_golden(&self, path: impl AsRef<Path>) {
+        let path = path.as_ref();
+        if std::env::var_os("WEPD_BLESS").is_some() {
+            if let Some(dir) = path.parent() {
+                fs::create_dir_all(dir).unwrap();
+            }
+            self.write_pbm(fs::File::create(path).unwrap()).unwrap();
+            return;
+        }
+
+        let golden = match fs::File::open(path).and_then(Self::read_pbm) {
+            Ok(golden) => golden,
+            Err(e) => {
+                self.write_pbm(fs::File::create(sibling(path, "actual")).unwrap())
+                    .unwrap();
+                panic!(
+                    "could not read golden image {}: {e} (set WEPD_BLESS to create it)",
+                    path.display()
+                );
+            }
+        };
+        let Some(diff) = self.diff(&golden) else {
+            return;
+        };
+
+        self.write_pbm(fs::File::create(sibling(path, "actual")).unwrap())
+            .unwrap();
+        diff.write_pbm(fs::File::create(sibling(path, "diff")).unwrap())
+            .unwrap();
+        eprintln!("{}", diff.to_ascii());
+        panic!(
+            "snapshot does not match golden image {}: {} pixels differ",
+            path.display(),
+            diff.count_black()
+        );
+    }
+
+    fn count_black(&self) -> usize {
+        (0..self.height)
+            .flat_map(|y| (0..self.width).map(move |x| (x, y)))
+            .filter(|&(x, y)| !self.pixel(x, y))
+            .count()
+    }
+
+    /// Renders the bounding box of the black pixels as text, for printing diffs.
+    fn to_ascii(&self) -> String {
+        let black: Vec<(u16, u16)> = (0..self.height)
+            .flat_map(|y| (0..self.width).map(move |x| (x, y)))
+            .filter(|&(x, y)| !self.pixel(x, y))
+            .collect();
+        let Some(x_lo) = black.iter().map(|p| p.0).min() else {
+            return String::new();
+        };
+        let x_hi = black.iter().map(|p| p.0).max().unwrap();
+        let y_lo = black.iter().map(|p| p.1).min().unwrap();
+        let y_hi = black.iter().map(|p| p.1).max().unwrap();
+
+        let mut out = format!("differences in x {x_lo}..={x_hi}, y {y_lo}..={y_hi}:\n");
+        for y in y_lo..=y_hi {
+            for x in x_lo..=x_hi {
+                out.push(if self.pixel(x, y) { '.' } else { '#' });
+            }
+            writeln!(out).unwrap();
+        }
+        out
+    }
+}
+
+#[cfg(feature = "embedded-graphics")]
+impl<P: crate::Panel> From<&crate::Framebuffer<P>> for Snapshot {
+    fn from(framebuffer: &crate::Framebuffer<P>) -> Self {
+        Self::new(P::WIDTH, P::HEIGHT, framebuffer.buffer().into())
+    }
+}
+
+#[cfg(feature = "sim")]
+impl From<&crate::sim::Simulator> for Snapshot {
+    /// Takes a snapshot of what the simulated panel shows.
+    fn from(sim: &crate::sim::Simulator) -> Self {
+        Self::new(sim.width(), sim.height(), sim.visible())
+    }
+}
+
+fn invalid_data(message: &str) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, message)
+}
+
+/// Turns `dir/name.pbm` into `dir/name.<suffix>.pbm`.
+fn sibling(path: &Path, suffix: &str) -> PathBuf {
+    let stem = path.file_stem().unwrap_or_default().to_string_lossy();
+    path.with_file_name(format!("{stem}.{suffix}.pbm"))
+}
+
+/// Just enough of PNG to write uncompressed images.
+#[cfg(feature = "png")]
+mod png {
+    use std::{io, vec, vec::Vec};
+
+    pub(super) fn write_chunk(
+        mut writer: impl io::Write,
+        kind: &[u8; 4],
+        data: &[u8],
+    ) -> io::Result<()> {
+        writer.write_all(&(data.len() as u32).to_be_bytes())?;
+        writer.write_all(kind)?;
+        writer.write_all(data)?;
+        let crc = crc32(kind.iter().chain(data));
+        writer.write_all(&crc.to_be_bytes())
+    }
+
+    /// Wraps `data` in a zlib stream made of stored (uncompressed) deflate blocks.
+    pub(super) fn zlib_stored(data: &[u8]) -> Vec<u8> {
+        let mut out = vec![0x78, 0x01];
+        let mut blocks = data.chunks(0xFFFF).peekable();
+        if blocks.peek().is_none() {
+            out.extend_from_slice(&[1, 0, 0, 0xFF, 0xFF]);
+        }
+        while let Some(block) = blocks.next() {
+            let last = blocks.peek().is_none();
+            let len = block.len() as u16;
+            out.push(last as u8);
+            out.extend_from_slice(&len.to_le_bytes());
+            out.extend_from_slice(&(!len).to_le_bytes());
+            out.extend_from_slice(block);
+        }
+        out.extend_from_slice(&adler32(data).to_be_bytes());
+        out
+    }
+
+    fn crc32<'a>(data: impl Iterator<Item = &'a u8>) -> u32 {
+        let mut crc = !0u32;
+        for &byte in data {
+            crc ^= byte as u32;
+            for _ in 0..8 {
+                crc = if crc & 1 != 0 {
+                    (crc >> 1) ^ 0xEDB8_8320
+                } else {
+                    crc >> 1
+                };
+            }
+        }
+        !crc
+    }
+
+    fn adler32(data: &[u8]) -> u32 {
+        let (mut a, mut b) = (1u32, 0u32);
+        for &byte in data {
+            a = (a + byte as u32) % 65521;
+            b = (b + a) % 65521;
+        }
+        (b << 16) | a
+    }
+}
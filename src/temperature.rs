@@ -0,0 +1,38 @@
+/// A temperature as used by the controller's temperature register, in units of 1/16 °C.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord)]
+pub struct Temperature(i16);
+
+impl Temperature {
+    /// Creates a temperature from a value in 1/16 °C. The register is 12 bits wide, so the value
+    /// is clamped to the range it can hold (-128 °C to just under 128 °C).
+    pub const fn from_sixteenths(sixteenths: i16) -> Self {
+        Self(if sixteenths < -2048 {
+            -2048
+        } else if sixteenths > 2047 {
+            2047
+        } else {
+            sixteenths
+        })
+    }
+
+    pub const fn from_celsius(celsius: i8) -> Self {
+        Self(celsius as i16 * 16)
+    }
+
+    pub const fn sixteenths(self) -> i16 {
+        self.0
+    }
+
+    /// Returns the temperature in whole degrees Celsius, rounded down.
+    pub const fn celsius(self) -> i16 {
+        self.0 >> 4
+    }
+
+    pub(crate) fn from_register(bytes: [u8; 2]) -> Self {
+        Self(((bytes[0] as i8 as i16) << 4) | (bytes[1] >> 4) as i16)
+    }
+
+    pub(crate) fn to_register(self) -> [u8; 2] {
+        [(self.0 >> 4) as u8, ((self.0 & 0xF) << 4) as u8]
+    }
+}
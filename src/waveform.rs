@@ -0,0 +1,109 @@
+/// A custom waveform for the controller, along with the voltages it is meant to be driven with.
+///
+/// The field layout follows the 159-byte tables found in vendor sample code, which can be
+/// converted with [Waveform::from_bytes].
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Waveform {
+    /// The waveform LUT itself, written with command 0x32.
+    pub lut: [u8; 153],
+    /// The end option (EOPT), written with command 0x3F.
+    pub end_option: u8,
+    /// The gate driving voltage (VGH), written with command 0x03.
+    pub gate_voltage: u8,
+    /// The source driving voltages (VSH1, VSH2 and VSL), written with command 0x04.
+    pub source_voltage: [u8; 3],
+    /// The VCOM voltage, written with command 0x2C.
+    pub vcom: u8,
+}
+
+impl Waveform {
+    /// The waveform used by the vendor sample code for full refreshes.
+    pub const FULL: Waveform = Waveform::from_bytes(&[
+        0x80, 0x48, 0x40, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, //
+        0x40, 0x48, 0x80, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, //
+        0x80, 0x48, 0x40, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, //
+        0x40, 0x48, 0x80, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, //
+        0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, //
+        0x0A, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, //
+        0x08, 0x01, 0x00, 0x08, 0x01, 0x00, 0x02, //
+        0x0A, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, //
+        0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, //
+        0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, //
+        0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, //
+        0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, //
+        0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, //
+        0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, //
+        0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, //
+        0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, //
+        0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, //
+        0x22, 0x22, 0x22, 0x22, 0x22, 0x22, 0x00, 0x00, 0x00, //
+        0x22, 0x17, 0x41, 0x00, 0x32, 0x20,
+    ]);
+
+    /// A short waveform for partial refreshes. It is noticeably faster than the OTP waveform, at
+    /// the cost of more ghosting.
+    pub const PARTIAL_FAST: Waveform = Waveform::from_bytes(&[
+        0x00, 0x40, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, //
+        0x80, 0x80, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, //
+        0x40, 0x40, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, //
+        0x00, 0x80, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, //
+        0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, //
+        0x0F, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, //
+        0x01, 0x01, 0x00, 0x00, 0x00, 0x00, 0x00, //
+        0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, //
+        0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, //
+        0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, //
+        0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, //
+        0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, //
+        0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, //
+        0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, //
+        0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, //
+        0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, //
+        0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, //
+        0x22, 0x22, 0x22, 0x22, 0x22, 0x22, 0x00, 0x00, 0x00, //
+        0x02, 0x17, 0x41, 0xB0, 0x32, 0x28,
+    ]);
+
+    /// A waveform that shows four gray levels, using the black and white RAM for the low bit and
+    /// the red RAM for the high bit of each pixel. See [crate::Display::draw_image_gray2].
+    pub const GRAYSCALE: Waveform = Waveform::from_bytes(&[
+        0x00, 0x60, 0x10, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, //
+        0x20, 0x60, 0x10, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, //
+        0x28, 0x60, 0x14, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, //
+        0x2A, 0x60, 0x15, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, //
+        0x00, 0x90, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, //
+        0x00, 0x02, 0x00, 0x05, 0x14, 0x00, 0x00, //
+        0x1E, 0x1E, 0x00, 0x00, 0x00, 0x00, 0x01, //
+        0x00, 0x02, 0x00, 0x05, 0x14, 0x00, 0x00, //
+        0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, //
+        0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, //
+        0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, //
+        0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, //
+        0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, //
+        0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, //
+        0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, //
+        0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, //
+        0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, //
+        0x24, 0x22, 0x22, 0x22, 0x23, 0x32, 0x00, 0x00, 0x00, //
+        0x22, 0x17, 0x41, 0xAE, 0x32, 0x28,
+    ]);
+
+    /// Splits a 159-byte table in the layout used by vendor sample code: the LUT followed by
+    /// EOPT, VGH, VSH1, VSH2, VSL and VCOM.
+    pub const fn from_bytes(bytes: &[u8; 159]) -> Self {
+        let mut lut = [0; 153];
+        let mut i = 0;
+        while i < lut.len() {
+            lut[i] = bytes[i];
+            i += 1;
+        }
+
+        Self {
+            lut,
+            end_option: bytes[153],
+            gate_voltage: bytes[154],
+            source_voltage: [bytes[155], bytes[156], bytes[157]],
+            vcom: bytes[158],
+        }
+    }
+}
@@ -0,0 +1,227 @@
+#![cfg(all(feature = "sim", feature = "async"))]
+
+use core::{
+    cell::Cell,
+    convert::Infallible,
+    future::{pending, Future},
+    pin::pin,
+    task::{Context, Poll, Waker},
+};
+
+use embedded_hal::digital::{ErrorType, InputPin};
+use embedded_hal_async::digital::Wait;
+use wepd::{
+    sim::{SimDelay, Simulator},
+    BusyTimeout, BusyWait, DelayWaiter, Display, DisplayConfiguration, DisplayError,
+};
+
+/// A busy pin that never goes low, like one on a panel that is missing or stuck.
+struct StuckBusy;
+
+impl ErrorType for StuckBusy {
+    type Error = Infallible;
+}
+
+impl InputPin for StuckBusy {
+    fn is_high(&mut self) -> Result<bool, Infallible> {
+        Ok(true)
+    }
+
+    fn is_low(&mut self) -> Result<bool, Infallible> {
+        Ok(false)
+    }
+}
+
+impl Wait for StuckBusy {
+    async fn wait_for_high(&mut self) -> Result<(), Infallible> {
+        Ok(())
+    }
+
+    async fn wait_for_low(&mut self) -> Result<(), Infallible> {
+        pending().await
+    }
+
+    async fn wait_for_rising_edge(&mut self) -> Result<(), Infallible> {
+        pending().await
+    }
+
+    async fn wait_for_falling_edge(&mut self) -> Result<(), Infallible> {
+        pending().await
+    }
+
+    async fn wait_for_any_edge(&mut self) -> Result<(), Infallible> {
+        pending().await
+    }
+}
+
+/// A busy pin whose interrupts never fire, which is high for the first `high_reads` reads of
+/// its level.
+struct DeafBusy {
+    high_reads: u32,
+}
+
+impl ErrorType for DeafBusy {
+    type Error = Infallible;
+}
+
+impl InputPin for DeafBusy {
+    fn is_high(&mut self) -> Result<bool, Infallible> {
+        let high = self.high_reads > 0;
+        self.high_reads = self.high_reads.saturating_sub(1);
+        Ok(high)
+    }
+
+    fn is_low(&mut self) -> Result<bool, Infallible> {
+        self.is_high().map(|high| !high)
+    }
+}
+
+impl Wait for DeafBusy {
+    async fn wait_for_high(&mut self) -> Result<(), Infallible> {
+        panic!("waited for an edge")
+    }
+
+    async fn wait_for_low(&mut self) -> Result<(), Infallible> {
+        panic!("waited for an edge")
+    }
+
+    async fn wait_for_rising_edge(&mut self) -> Result<(), Infallible> {
+        panic!("waited for an edge")
+    }
+
+    async fn wait_for_falling_edge(&mut self) -> Result<(), Infallible> {
+        panic!("waited for an edge")
+    }
+
+    async fn wait_for_any_edge(&mut self) -> Result<(), Infallible> {
+        panic!("waited for an edge")
+    }
+}
+
+/// A busy waiter that times out at once, counting how often it was asked to wait.
+struct CountingWaiter<'a> {
+    waits: &'a Cell<u32>,
+}
+
+impl<Busy: Wait> BusyWait<Busy> for CountingWaiter<'_> {
+    async fn wait(&mut self, _busy: &mut Busy) -> Result<Result<(), BusyTimeout>, Busy::Error> {
+        self.waits.set(self.waits.get() + 1);
+        Ok(Err(BusyTimeout))
+    }
+}
+
+/// Runs `future`, which must not wait on anything but the simulator's instant delays.
+fn block_on<F: Future>(future: F) -> F::Output {
+    let mut future = pin!(future);
+    let mut cx = Context::from_waker(Waker::noop());
+    for _ in 0..1_000_000 {
+        if let Poll::Ready(output) = future.as_mut().poll(&mut cx) {
+            return output;
+        }
+    }
+    panic!("future did not finish");
+}
+
+#[test]
+fn delay_waiter_times_out() {
+    let mut waiter = DelayWaiter::new(SimDelay).with_timeout_ms(50);
+    assert!(matches!(block_on(waiter.wait(&mut StuckBusy)), Ok(Err(BusyTimeout))));
+}
+
+#[test]
+fn polling_delay_waiter_times_out() {
+    let mut waiter = DelayWaiter::new(SimDelay)
+        .with_delay_ms(5)
+        .with_timeout_ms(50)
+        .polling();
+    assert!(matches!(block_on(waiter.wait(&mut StuckBusy)), Ok(Err(BusyTimeout))));
+}
+
+#[test]
+fn polling_waiter_reads_the_level_instead_of_waiting_for_edges() {
+    let mut waiter = DelayWaiter::new(SimDelay).with_timeout_ms(50).polling();
+    let mut busy = DeafBusy { high_reads: 10 };
+    assert!(matches!(block_on(waiter.wait(&mut busy)), Ok(Ok(()))));
+    assert_eq!(busy.high_reads, 0);
+
+    let mut busy = DeafBusy { high_reads: 100 };
+    assert!(matches!(block_on(waiter.wait(&mut busy)), Ok(Err(BusyTimeout))));
+}
+
+#[test]
+fn delay_waiter_returns_when_not_busy() {
+    let sim = Simulator::new(200, 200);
+    let mut waiter = DelayWaiter::new(SimDelay);
+    assert!(matches!(block_on(waiter.wait(&mut sim.busy())), Ok(Ok(()))));
+    let mut waiter = DelayWaiter::new(SimDelay).polling();
+    assert!(matches!(block_on(waiter.wait(&mut sim.busy())), Ok(Ok(()))));
+}
+
+#[test]
+fn stuck_panel_reports_busy_timeout() {
+    let sim = Simulator::new(200, 200);
+    let mut display = Display::new(DisplayConfiguration {
+        spi: sim.spi(),
+        dc: sim.dc(),
+        rst: sim.rst(),
+        busy: StuckBusy,
+        delay: SimDelay,
+        busy_wait: DelayWaiter::new(SimDelay).with_timeout_ms(50),
+    })
+    .unwrap();
+
+    let result = block_on(display.clear_screen(0xFF));
+    assert!(matches!(result, Err(DisplayError::BusyTimeout)));
+}
+
+#[test]
+fn diagnose_tells_stuck_panel_from_missing_one() {
+    for connected in [true, false] {
+        let sim = Simulator::new(200, 200);
+        sim.set_connected(connected);
+        let mut display = Display::new(DisplayConfiguration {
+            spi: sim.spi(),
+            dc: sim.dc(),
+            rst: sim.rst(),
+            busy: StuckBusy,
+            delay: SimDelay,
+            busy_wait: DelayWaiter::new(SimDelay).with_timeout_ms(50),
+        })
+        .unwrap();
+
+        // Both look busy forever, but only a panel that is there reads back its status
+        let health = block_on(display.diagnose()).unwrap();
+        assert!(!health.busy_responsive);
+        assert_eq!(health.booster_ok.is_some(), connected);
+    }
+}
+
+#[test]
+fn diagnose_sees_busy_line_go_high() {
+    let sim = Simulator::new(200, 200);
+    let mut display = Display::new(sim.config()).unwrap();
+
+    let health = block_on(display.diagnose()).unwrap();
+    assert!(health.responding && health.busy_responsive);
+    assert_eq!(health.vci_ok, Some(true));
+}
+
+#[test]
+fn diagnose_waits_for_stuck_panel_only_once() {
+    let sim = Simulator::new(200, 200);
+    let waits = Cell::new(0);
+    let mut display = Display::new(DisplayConfiguration {
+        spi: sim.spi(),
+        dc: sim.dc(),
+        rst: sim.rst(),
+        busy: StuckBusy,
+        delay: SimDelay,
+        busy_wait: CountingWaiter { waits: &waits },
+    })
+    .unwrap();
+    waits.set(0);
+
+    let health = block_on(display.diagnose()).unwrap();
+    assert!(!health.busy_responsive);
+    assert_eq!(waits.get(), 1);
+}
@@ -0,0 +1,153 @@
+#![cfg(feature = "embedded-graphics")]
+
+use embedded_graphics_core::{pixelcolor::BinaryColor, prelude::*, primitives::Rectangle};
+use wepd::{Framebuffer, Gdey0213b74, Rotation};
+
+const ROTATIONS: [Rotation; 4] = [
+    Rotation::Deg0,
+    Rotation::Deg90,
+    Rotation::Deg180,
+    Rotation::Deg270,
+];
+
+fn areas() -> Vec<Rectangle> {
+    vec![
+        Rectangle::new(Point::new(3, 5), Size::new(1, 1)),
+        Rectangle::new(Point::new(3, 5), Size::new(4, 9)),
+        Rectangle::new(Point::new(8, 0), Size::new(16, 3)),
+        Rectangle::new(Point::new(5, 7), Size::new(100, 1)),
+        Rectangle::new(Point::new(17, 2), Size::new(1, 90)),
+        Rectangle::new(Point::new(-10, -4), Size::new(30, 20)),
+        Rectangle::new(Point::new(110, 240), Size::new(50, 50)),
+        Rectangle::new(Point::new(300, 300), Size::new(5, 5)),
+        Rectangle::new(Point::new(0, 0), Size::new(0, 10)),
+    ]
+}
+
+/// Draws `area` pixel by pixel, which the specialized methods must agree with.
+fn fill_slowly(framebuffer: &mut Framebuffer<Gdey0213b74>, area: &Rectangle, color: BinaryColor) {
+    framebuffer
+        .draw_iter(area.points().map(|point| Pixel(point, color)))
+        .unwrap();
+}
+
+fn framebuffer(rotation: Rotation) -> Framebuffer<Gdey0213b74> {
+    let mut framebuffer = Framebuffer::with_panel(Gdey0213b74);
+    framebuffer.set_rotation(rotation);
+    // Start from a pattern so that both colors are visible in the result
+    let bounds = framebuffer.bounding_box();
+    framebuffer
+        .draw_iter(
+            bounds
+                .points()
+                .map(|p| Pixel(p, BinaryColor::from((p.x + p.y) % 3 == 0))),
+        )
+        .unwrap();
+    framebuffer
+}
+
+#[test]
+fn fill_solid_matches_pixels() {
+    for rotation in ROTATIONS {
+        for area in areas() {
+            for color in [BinaryColor::On, BinaryColor::Off] {
+                let mut fast = framebuffer(rotation);
+                let mut slow = framebuffer(rotation);
+                fast.fill_solid(&area, color).unwrap();
+                fill_slowly(&mut slow, &area, color);
+                assert_eq!(fast.buffer(), slow.buffer(), "{rotation:?} {area:?} {color:?}");
+            }
+        }
+    }
+}
+
+#[test]
+fn fill_contiguous_matches_pixels() {
+    for rotation in ROTATIONS {
+        for area in areas() {
+            let colors = || area.points().map(|p| BinaryColor::from((p.x * 7 + p.y) % 5 < 2));
+            let mut fast = framebuffer(rotation);
+            let mut slow = framebuffer(rotation);
+            fast.fill_contiguous(&area, colors()).unwrap();
+            slow.draw_iter(area.points().zip(colors()).map(|(p, c)| Pixel(p, c)))
+                .unwrap();
+            assert_eq!(fast.buffer(), slow.buffer(), "{rotation:?} {area:?}");
+        }
+    }
+}
+
+#[test]
+fn fill_contiguous_stops_when_colors_run_out() {
+    for rotation in ROTATIONS {
+        for area in areas() {
+            let count = area.points().count() * 2 / 3;
+            let colors = || area.points().map(|p| BinaryColor::from(p.x % 3 == 0)).take(count);
+            let mut fast = framebuffer(rotation);
+            let mut slow = framebuffer(rotation);
+            fast.fill_contiguous(&area, colors()).unwrap();
+            slow.draw_iter(area.points().zip(colors()).map(|(p, c)| Pixel(p, c)))
+                .unwrap();
+            assert_eq!(fast.buffer(), slow.buffer(), "{rotation:?} {area:?}");
+        }
+    }
+}
+
+#[test]
+fn clear_fills_everything() {
+    let mut framebuffer = framebuffer(Rotation::Deg90);
+    framebuffer.clear(BinaryColor::Off).unwrap();
+    assert!(framebuffer.buffer().iter().all(|&b| b == 0x00));
+}
+
+/// Returns whether the panel pixel at `(x, y)` is black in a Gdey0213b74 buffer.
+fn is_black(buffer: &[u8], x: usize, y: usize) -> bool {
+    let stride = 122_usize.div_ceil(8);
+    buffer[y * stride + x / 8] & (0x80 >> (x % 8)) == 0
+}
+
+#[test]
+fn rotation_maps_points_to_panel() {
+    let cases = [
+        (Rotation::Deg0, Point::new(0, 0), (0, 0)),
+        (Rotation::Deg0, Point::new(5, 3), (5, 3)),
+        (Rotation::Deg90, Point::new(0, 0), (121, 0)),
+        (Rotation::Deg90, Point::new(5, 3), (118, 5)),
+        (Rotation::Deg180, Point::new(0, 0), (121, 249)),
+        (Rotation::Deg180, Point::new(5, 3), (116, 246)),
+        (Rotation::Deg270, Point::new(0, 0), (0, 249)),
+        (Rotation::Deg270, Point::new(5, 3), (3, 244)),
+    ];
+    for (rotation, point, (x, y)) in cases {
+        let mut framebuffer = Framebuffer::with_panel(Gdey0213b74);
+        framebuffer.set_rotation(rotation);
+        Pixel(point, BinaryColor::Off)
+            .draw(&mut framebuffer)
+            .unwrap();
+
+        let buffer = framebuffer.buffer();
+        assert!(is_black(buffer, x, y), "{rotation:?} {point:?}");
+        let black = (0..250)
+            .flat_map(|y| (0..122).map(move |x| (x, y)))
+            .filter(|&(x, y)| is_black(buffer, x, y))
+            .count();
+        assert_eq!(black, 1, "{rotation:?} {point:?}");
+    }
+}
+
+#[test]
+fn rotation_swaps_bounding_box() {
+    for (rotation, size) in [
+        (Rotation::Deg0, Size::new(122, 250)),
+        (Rotation::Deg90, Size::new(250, 122)),
+        (Rotation::Deg180, Size::new(122, 250)),
+        (Rotation::Deg270, Size::new(250, 122)),
+    ] {
+        let mut framebuffer = Framebuffer::with_panel(Gdey0213b74);
+        framebuffer.set_rotation(rotation);
+        assert_eq!(
+            framebuffer.bounding_box(),
+            Rectangle::new(Point::zero(), size),
+            "{rotation:?}"
+        );
+    }
+}
@@ -0,0 +1,439 @@
+#![cfg(all(feature = "sim", not(feature = "async")))]
+
+use wepd::{
+    command,
+    record::{Event, Recorder},
+    sim::{SimBusy, SimDc, SimDelay, SimRst, SimSpi, Simulator},
+    Border, DelayWaiter, Display, DisplayConfiguration, DisplayState, Gdey0213b74, Temperature,
+    Waveform,
+};
+
+use Event::{Command, Data, Reset};
+
+type RecordedConfiguration = DisplayConfiguration<
+    wepd::record::RecordingSpi<SimSpi>,
+    wepd::record::RecordingDc<SimDc>,
+    wepd::record::RecordingRst<SimRst>,
+    SimBusy,
+    SimDelay,
+    DelayWaiter<SimDelay>,
+>;
+
+fn display() -> (Display<RecordedConfiguration>, Recorder) {
+    let sim = Simulator::new(200, 200);
+    let recorder = Recorder::new();
+    let display = Display::new(DisplayConfiguration {
+        spi: recorder.spi(sim.spi()),
+        dc: recorder.dc(sim.dc()),
+        rst: recorder.rst(sim.rst()),
+        busy: sim.busy(),
+        delay: SimDelay,
+        busy_wait: DelayWaiter::new(SimDelay),
+    })
+    .unwrap();
+    recorder.take();
+
+    (display, recorder)
+}
+
+fn data(bytes: &[u8]) -> Event {
+    Data(bytes.to_vec())
+}
+
+fn init_sequence() -> Vec<Event> {
+    vec![
+        Command(0x01),
+        data(&[0xC7, 0x00, 0x00]),
+        Command(0x18),
+        data(&[0x80]),
+        Command(0x3C),
+        data(&[0x05]),
+        Command(0x11),
+        data(&[0x03]),
+        Command(0x44),
+        data(&[0x00, 0x18]),
+        Command(0x45),
+        data(&[0x00, 0x00, 0xC7, 0x00]),
+        Command(0x4E),
+        data(&[0x00]),
+        Command(0x4F),
+        data(&[0x00, 0x00]),
+        Command(0x22),
+        data(&[0xF8]),
+        Command(0x20),
+    ]
+}
+
+fn ram_area(x_lo: u8, x_hi: u8, y_lo: u16, y_hi: u16) -> Vec<Event> {
+    let [y_lo_l, y_lo_h] = y_lo.to_le_bytes();
+    let [y_hi_l, y_hi_h] = y_hi.to_le_bytes();
+    vec![
+        Command(0x11),
+        data(&[0x03]),
+        Command(0x44),
+        data(&[x_lo, x_hi]),
+        Command(0x45),
+        data(&[y_lo_l, y_lo_h, y_hi_l, y_hi_h]),
+        Command(0x4E),
+        data(&[x_lo]),
+        Command(0x4F),
+        data(&[y_lo_l, y_lo_h]),
+    ]
+}
+
+/// Returns the display update control (0x22) values in `events`.
+fn update_controls(events: &[Event]) -> Vec<u8> {
+    events
+        .windows(2)
+        .filter_map(|pair| match pair {
+            [Command(0x22), Data(data)] => Some(data[0]),
+            _ => None,
+        })
+        .collect()
+}
+
+#[test]
+fn reset_pulses_rst() {
+    let (mut display, recorder) = display();
+
+    display.reset().unwrap();
+
+    assert_eq!(recorder.events(), [Reset(false), Reset(true)]);
+}
+
+#[test]
+fn first_clear_screen_initializes_and_refreshes_fully() {
+    let (mut display, recorder) = display();
+
+    display.clear_screen(0xFF).unwrap();
+
+    let mut expected = init_sequence();
+    expected.extend([
+        Command(0x26),
+        data(&[0xFF; 5000]),
+        Command(0x24),
+        data(&[0xFF; 5000]),
+        Command(0x22),
+        data(&[0xF4]),
+        Command(0x20),
+        Command(0x24),
+        data(&[0xFF; 5000]),
+    ]);
+    assert_eq!(recorder.events(), expected);
+}
+
+#[test]
+fn clear_screen_fills_ram_in_chunks() {
+    let (mut display, recorder) = display();
+    display.clear_screen(0xFF).unwrap();
+    recorder.take();
+
+    display.set_fill_chunk(100);
+    display.clear_screen(0x00).unwrap();
+
+    // Each command is a transaction of its own, and each 5000 byte fill takes 50
+    let commands = recorder.commands().len();
+    let params = recorder
+        .events()
+        .iter()
+        .filter(|event| matches!(event, Data(data) if data.len() < 100))
+        .count();
+    assert_eq!(recorder.transactions(), commands + params + 2 * 50);
+}
+
+#[test]
+fn draw_image_after_clear_refreshes_partially() {
+    let (mut display, recorder) = display();
+    display.clear_screen(0xFF).unwrap();
+    recorder.take();
+
+    display
+        .draw_image(&[0x00; 8], 16, 10, 32, 14)
+        .unwrap();
+
+    let mut expected = ram_area(2, 3, 10, 13);
+    expected.extend([Command(0x24), data(&[0x00; 8])]);
+    expected.extend(ram_area(2, 3, 10, 13));
+    expected.extend([Command(0x22), data(&[0xFC]), Command(0x20)]);
+    expected.extend(ram_area(2, 3, 10, 13));
+    expected.extend([Command(0x24), data(&[0x00; 8])]);
+    assert_eq!(recorder.events(), expected);
+}
+
+#[test]
+fn ram_area_sends_high_bytes_of_y() {
+    let (mut display, recorder) = display();
+    display.clear_screen(0xFF).unwrap();
+    recorder.take();
+
+    display.write_image(&[0x00; 25], 0, 199, 200, 200).unwrap();
+
+    let mut expected = ram_area(0, 24, 199, 199);
+    expected.extend([Command(0x24), data(&[0x00; 25])]);
+    assert_eq!(recorder.events(), expected);
+}
+
+#[test]
+fn power_off_only_when_on() {
+    let (mut display, recorder) = display();
+
+    display.power_off().unwrap();
+    assert_eq!(recorder.take(), []);
+
+    display.clear_screen(0xFF).unwrap();
+    recorder.take();
+    display.power_off().unwrap();
+    assert_eq!(
+        recorder.take(),
+        [Command(0x22), data(&[0x83]), Command(0x20)]
+    );
+}
+
+#[test]
+fn draw_after_power_off_initializes_again() {
+    let (mut display, recorder) = display();
+    display.clear_screen(0xFF).unwrap();
+    display.power_off().unwrap();
+    recorder.take();
+
+    display.write_image(&[0x00; 25], 0, 0, 200, 1).unwrap();
+
+    let events = recorder.events();
+    assert_eq!(events[..init_sequence().len()], init_sequence());
+}
+
+#[test]
+fn hibernate_sleeps_and_wakes_with_reset() {
+    let (mut display, recorder) = display();
+    display.clear_screen(0xFF).unwrap();
+    recorder.take();
+
+    display.hibernate().unwrap();
+    assert_eq!(
+        recorder.take(),
+        [
+            Command(0x22),
+            data(&[0x83]),
+            Command(0x20),
+            Command(0x10),
+            data(&[0x01]),
+        ]
+    );
+
+    display.write_image(&[0x00; 25], 0, 0, 200, 1).unwrap();
+    let events = recorder.events();
+    assert_eq!(events[..2], [Reset(false), Reset(true)]);
+    assert_eq!(events[2..2 + init_sequence().len()], init_sequence());
+}
+
+#[test]
+fn narrow_panel_sets_gates_and_source_output_mode() {
+    let sim = Simulator::new(122, 250);
+    let recorder = Recorder::new();
+    let mut display = Display::with_panel(
+        DisplayConfiguration {
+            spi: recorder.spi(sim.spi()),
+            dc: recorder.dc(sim.dc()),
+            rst: recorder.rst(sim.rst()),
+            busy: sim.busy(),
+            delay: SimDelay,
+            busy_wait: DelayWaiter::new(SimDelay),
+        },
+        Gdey0213b74,
+        DisplayState::new(),
+    )
+    .unwrap();
+    recorder.take();
+
+    display.refresh_full().unwrap();
+
+    let events = recorder.take();
+    assert_eq!(
+        events[..4],
+        [
+            Command(0x01),
+            data(&[0xF9, 0x00, 0x00]),
+            Command(0x21),
+            data(&[0x00, 0x80]),
+        ]
+    );
+    assert!(events.windows(2).any(|w| w == [Command(0x44), data(&[0x00, 0x0F])]));
+    assert!(events.windows(2).any(|w| w == [Command(0x22), data(&[0xE0])]));
+}
+
+#[test]
+fn send_encodes_parameters() {
+    let (mut display, recorder) = display();
+
+    display
+        .send(command::Command::RamYAddress {
+            start: 0x0102,
+            end: 0x0127,
+        })
+        .unwrap();
+    display
+        .send(command::Command::DriverOutputControl {
+            last_gate: 295,
+            scanning: 0x01,
+        })
+        .unwrap();
+    display.send(command::Command::MasterActivation).unwrap();
+    display.send(command::Command::Other(0x3f, &[0x22])).unwrap();
+    display
+        .send(command::Command::DataEntryMode(command::DataEntryMode::DecrementXIncrementY))
+        .unwrap();
+    display.send(command::Command::BorderWaveform(Border::HiZ)).unwrap();
+    display
+        .send(command::Command::BorderWaveform(Border::Black.for_panel::<Gdey0213b74>()))
+        .unwrap();
+
+    assert_eq!(
+        recorder.events(),
+        [
+            Command(0x45),
+            data(&[0x02, 0x01, 0x27, 0x01]),
+            Command(0x01),
+            data(&[0x27, 0x01, 0x01]),
+            Command(0x20),
+            Command(0x3f),
+            data(&[0x22]),
+            Command(0x11),
+            data(&[0x02]),
+            Command(0x3c),
+            data(&[0xc0]),
+            Command(0x3c),
+            data(&[0x02]),
+        ]
+    );
+}
+
+#[test]
+fn full_waveform_is_loaded_once_and_used_for_full_refreshes() {
+    let (mut display, recorder) = display();
+    display.set_full_waveform(Some(&Waveform::FULL));
+
+    display.refresh_full().unwrap();
+    display.refresh_full().unwrap();
+
+    let commands = recorder.commands();
+    for command in [0x32, 0x3f, 0x03, 0x04, 0x2c] {
+        let count = commands.iter().filter(|&&c| c == command).count();
+        assert_eq!(count, 1, "command {command:#04x}");
+    }
+    let events = recorder.take();
+    assert!(events.contains(&data(&Waveform::FULL.lut)));
+    // Powering on loads the OTP waveform, which the custom one then replaces
+    assert_eq!(update_controls(&events), [0xf8, 0xc4, 0xc4]);
+
+    display.set_full_waveform(None);
+    display.refresh_full().unwrap();
+
+    let events = recorder.take();
+    assert!(!events.contains(&Command(0x32)));
+    assert_eq!(update_controls(&events), [0xf4]);
+}
+
+#[test]
+fn draw_image_gray2_writes_planes_and_grayscale_waveform() {
+    let (mut display, recorder) = display();
+    display.clear_screen(0xFF).unwrap();
+    recorder.take();
+
+    display
+        .draw_image_gray2(&[0x0F], &[0x33], 8, 10, 16, 11)
+        .unwrap();
+
+    let events = recorder.take();
+    assert!(events.windows(2).any(|w| w == [Command(0x24), data(&[0x0F])]));
+    assert!(events.windows(2).any(|w| w == [Command(0x26), data(&[0x33])]));
+    assert!(events
+        .windows(2)
+        .any(|w| w == [Command(0x32), data(&Waveform::GRAYSCALE.lut)]));
+    assert_eq!(update_controls(&events), [0xc4]);
+}
+
+#[test]
+#[cfg(feature = "embedded-graphics")]
+fn gray2_framebuffer_splits_luma_into_planes() {
+    use embedded_graphics_core::{pixelcolor::Gray2, prelude::*};
+
+    let (mut display, recorder) = display();
+    let mut framebuffer = wepd::Gray2Framebuffer::new();
+    for luma in 0..4 {
+        Pixel(Point::new(i32::from(luma), 0), Gray2::new(luma))
+            .draw(&mut framebuffer)
+            .unwrap();
+    }
+
+    framebuffer.flush(&mut display).unwrap();
+
+    // Pixels 0 to 3 are black, dark gray, light gray and white, and the rest stay white
+    let mut low = vec![0xFF; 5000];
+    low[0] = 0b0101_1111;
+    let mut high = vec![0xFF; 5000];
+    high[0] = 0b0011_1111;
+    let events = recorder.take();
+    assert!(events.windows(2).any(|w| w == [Command(0x24), data(&low)]));
+    assert!(events.windows(2).any(|w| w == [Command(0x26), data(&high)]));
+}
+
+#[test]
+fn set_temperature_writes_it_before_each_update() {
+    let (mut display, recorder) = display();
+    display.set_temperature(Some(Temperature::from_celsius(25)));
+
+    display.clear_screen(0xFF).unwrap();
+    display.draw_image(&[0x00; 2], 0, 0, 16, 1).unwrap();
+
+    // The update controls no longer have the controller load its own sensor reading
+    let events = recorder.take();
+    assert_eq!(update_controls(&events), [0xf8, 0xd4, 0xdc]);
+    let updates: Vec<usize> = (0..events.len())
+        .filter(|&i| events[i] == Command(0x22))
+        .collect();
+    for &i in &updates[1..] {
+        assert_eq!(events[i - 2..i], [Command(0x1a), data(&[0x19, 0x00])]);
+    }
+}
+
+#[test]
+#[cfg(feature = "embedded-graphics")]
+fn framebuffer_flush_sends_only_the_dirty_byte() {
+    use embedded_graphics_core::{pixelcolor::BinaryColor, prelude::*};
+
+    let (mut display, recorder) = display();
+    let mut framebuffer = wepd::Framebuffer::new();
+    framebuffer.invalidate();
+    framebuffer.flush(&mut display).unwrap();
+    recorder.take();
+
+    Pixel(Point::new(20, 30), BinaryColor::Off)
+        .draw(&mut framebuffer)
+        .unwrap();
+    framebuffer.flush(&mut display).unwrap();
+
+    let mut expected = ram_area(2, 2, 30, 30);
+    expected.extend([Command(0x24), data(&[0xF7])]);
+    expected.extend(ram_area(2, 2, 30, 30));
+    expected.extend([Command(0x22), data(&[0xFC]), Command(0x20)]);
+    expected.extend(ram_area(2, 2, 30, 30));
+    expected.extend([Command(0x24), data(&[0xF7])]);
+    assert_eq!(recorder.take(), expected);
+
+    framebuffer.flush(&mut display).unwrap();
+    assert_eq!(recorder.take(), []);
+}
+
+#[test]
+fn empty_or_off_screen_draw_sends_nothing() {
+    let (mut display, recorder) = display();
+    display.clear_screen(0xFF).unwrap();
+    recorder.take();
+
+    display.draw_image(&[], 0, 0, 8, 0).unwrap();
+    display.draw_image(&[], 16, 16, 16, 24).unwrap();
+    display.draw_image(&[0x00; 2], 200, 0, 216, 1).unwrap();
+    display.draw_image(&[0x00; 2], -16, 0, 0, 1).unwrap();
+
+    assert_eq!(recorder.take(), []);
+}
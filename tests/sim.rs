@@ -0,0 +1,520 @@
+#![cfg(all(feature = "sim", not(feature = "async")))]
+
+use wepd::{
+    command::{Command, Ram},
+    sim::{SimDelay, Simulator},
+    Border, BusyTimeout, BusyWait, DelayWaiter, Display, DisplayState, Gdeh0154z90, Gdey0213b74,
+    RefreshPolicy, Temperature, Waveform,
+};
+
+#[test]
+fn clear_screen_fills_panel() {
+    let sim = Simulator::new(200, 200);
+    let mut display = Display::new(sim.config()).unwrap();
+
+    display.clear_screen(0x00).unwrap();
+
+    assert!(sim.visible().iter().all(|&b| b == 0x00));
+    assert!(sim.red_ram().iter().all(|&b| b == 0x00));
+}
+
+#[test]
+fn draw_image_lands_at_unaligned_position() {
+    let sim = Simulator::new(200, 200);
+    let mut display = Display::new(sim.config()).unwrap();
+    display.clear_screen(0xFF).unwrap();
+
+    // A 10x2 black bar at (13, 20)
+    let bitmap = [0x00, 0x3F, 0x00, 0x3F];
+    display.draw_image(&bitmap, 13, 20, 23, 22).unwrap();
+
+    for y in 18..24 {
+        for x in 0..32 {
+            let inside = (13..23).contains(&x) && (20..22).contains(&y);
+            assert_eq!(sim.pixel(x, y), !inside, "pixel ({x}, {y})");
+        }
+    }
+}
+
+#[test]
+fn draw_image_clips_to_screen() {
+    let sim = Simulator::new(200, 200);
+    let mut display = Display::new(sim.config()).unwrap();
+    display.clear_screen(0xFF).unwrap();
+
+    // A 16x4 black square hanging off the bottom right corner
+    let bitmap = [0x00; 8];
+    display.draw_image(&bitmap, 194, 198, 210, 202).unwrap();
+
+    assert!(!sim.pixel(194, 198));
+    assert!(!sim.pixel(199, 199));
+    assert!(sim.pixel(193, 198));
+    assert!(sim.pixel(194, 197));
+}
+
+#[test]
+fn draw_after_hibernate_wakes_controller() {
+    let sim = Simulator::new(200, 200);
+    let mut display = Display::new(sim.config()).unwrap();
+    display.clear_screen(0xFF).unwrap();
+
+    display.hibernate().unwrap();
+    assert!(sim.is_sleeping());
+
+    display.draw_image(&[0x00; 25], 0, 0, 200, 1).unwrap();
+    assert!(!sim.is_sleeping());
+    assert!(!sim.pixel(100, 0));
+    assert!(sim.pixel(100, 1));
+}
+
+#[test]
+fn read_temperature_from_sensor() {
+    let sim = Simulator::new(200, 200);
+    let mut display = Display::new(sim.config()).unwrap();
+    sim.set_temperature(-5 * 16);
+
+    assert_eq!(
+        display.read_temperature().unwrap(),
+        Temperature::from_celsius(-5)
+    );
+    // The clock is turned off again after loading the temperature
+    assert_eq!(sim.updates().last(), Some(&0xa1));
+}
+
+#[test]
+#[cfg(feature = "embedded-graphics")]
+fn framebuffer_reaches_corner_of_narrow_panel() {
+    use embedded_graphics_core::{pixelcolor::BinaryColor, prelude::*};
+
+    let sim = Simulator::new(122, 250);
+    let mut display = Display::with_panel(sim.config(), Gdey0213b74, DisplayState::new()).unwrap();
+    display.clear_screen(0xFF).unwrap();
+
+    let mut framebuffer = wepd::Framebuffer::with_panel(Gdey0213b74);
+    assert_eq!(framebuffer.bounding_box().size, Size::new(122, 250));
+    Pixel(Point::new(121, 249), BinaryColor::Off)
+        .draw(&mut framebuffer)
+        .unwrap();
+    framebuffer.flush(&mut display).unwrap();
+
+    assert!(!sim.pixel(121, 249));
+    assert!(sim.pixel(120, 249));
+    assert!(sim.pixel(121, 248));
+}
+
+#[test]
+fn draw_image_bwr_shows_both_planes() {
+    let sim = Simulator::tri_color(200, 200);
+    let mut display = Display::with_panel(sim.config(), Gdeh0154z90, DisplayState::new()).unwrap();
+    display.clear_screen(0xFF).unwrap();
+
+    // Black in the first byte of the row, red in the second
+    let black = [0x00, 0xFF];
+    let red = [0xFF, 0x00];
+    display.draw_image_bwr(&black, &red, 8, 10, 24, 11).unwrap();
+
+    assert!(!sim.pixel(8, 10) && !sim.red_pixel(8, 10));
+    assert!(sim.pixel(16, 10) && sim.red_pixel(16, 10));
+    assert!(sim.pixel(24, 10) && !sim.red_pixel(24, 10));
+    assert!(sim.updates().iter().all(|&control| control != 0xfc));
+}
+
+#[test]
+fn draw_image_bwr_needs_tri_color_panel() {
+    let sim = Simulator::new(200, 200);
+    let mut display = Display::new(sim.config()).unwrap();
+
+    let result = display.draw_image_bwr(&[0xFF], &[0xFF], 0, 0, 8, 1);
+    assert!(matches!(result, Err(wepd::DisplayError::Unsupported)));
+}
+
+#[test]
+fn draw_image_gray2_needs_black_and_white_panel() {
+    let sim = Simulator::tri_color(200, 200);
+    let mut display = Display::with_panel(sim.config(), Gdeh0154z90, DisplayState::new()).unwrap();
+
+    let result = display.draw_image_gray2(&[0xFF], &[0xFF], 0, 0, 8, 1);
+    assert!(matches!(result, Err(wepd::DisplayError::Unsupported)));
+    assert!(sim.updates().is_empty());
+}
+
+#[test]
+#[cfg(feature = "embedded-graphics")]
+fn window_draws_only_its_area() {
+    use embedded_graphics_core::{pixelcolor::BinaryColor, prelude::*, primitives::Rectangle};
+
+    let sim = Simulator::new(200, 200);
+    let mut display = Display::new(sim.config()).unwrap();
+    display.clear_screen(0xFF).unwrap();
+
+    // 20x4 pixels, widened to 24x4
+    let mut buffer = [0; 12];
+    let mut window = display.window(&mut buffer, 44, 30, 60, 34).unwrap();
+    assert_eq!(
+        window.bounding_box(),
+        Rectangle::new(Point::new(40, 30), Size::new(24, 4))
+    );
+    window
+        .fill_solid(
+            &Rectangle::new(Point::new(0, 0), Size::new(200, 200)),
+            BinaryColor::Off,
+        )
+        .unwrap();
+    window.commit().unwrap();
+
+    assert!(!sim.pixel(40, 30));
+    assert!(!sim.pixel(63, 33));
+    assert!(sim.pixel(39, 30));
+    assert!(sim.pixel(64, 30));
+    assert!(sim.pixel(40, 34));
+}
+
+#[test]
+#[cfg(feature = "embedded-graphics")]
+fn window_rejects_short_buffer() {
+    let sim = Simulator::new(200, 200);
+    let mut display = Display::new(sim.config()).unwrap();
+
+    let mut buffer = [0; 11];
+    let result = display.window(&mut buffer, 44, 30, 60, 34);
+    assert!(matches!(result, Err(wepd::DisplayError::InvalidBitmap)));
+}
+
+#[test]
+#[cfg(feature = "embedded-graphics")]
+fn draw_banded_matches_framebuffer() {
+    use embedded_graphics_core::{pixelcolor::BinaryColor, prelude::*, primitives::Rectangle};
+
+    // A rectangle crossing the band boundaries, and a pixel in the short last band
+    let picture = |target: &mut dyn FnMut(Rectangle)| {
+        target(Rectangle::new(Point::new(30, 20), Size::new(100, 70)));
+        target(Rectangle::new(Point::new(199, 199), Size::new(1, 1)));
+    };
+
+    let sim = Simulator::new(200, 200);
+    let mut display = Display::new(sim.config()).unwrap();
+    display.clear_screen(0xFF).unwrap();
+    let mut band = [0; 25 * 30];
+    let mut passes = 0;
+    display
+        .draw_banded(&mut band, |band| {
+            passes += 1;
+            assert_eq!(band.bounding_box().size, Size::new(200, 200));
+            picture(&mut |area| band.fill_solid(&area, BinaryColor::Off).unwrap());
+        })
+        .unwrap();
+
+    let mut framebuffer = wepd::Framebuffer::new();
+    picture(&mut |area| framebuffer.fill_solid(&area, BinaryColor::Off).unwrap());
+    assert_eq!(sim.visible(), framebuffer.buffer());
+    // One pass per band, and the controller keeps the previous image itself
+    assert_eq!(passes, 7);
+    assert_eq!(sim.red_ram(), framebuffer.buffer());
+}
+
+#[test]
+fn read_image_returns_ram() {
+    let sim = Simulator::new(200, 200);
+    let mut display = Display::new(sim.config()).unwrap();
+    display.clear_screen(0xFF).unwrap();
+    display
+        .draw_image(&[0x0F, 0xF0, 0x3C, 0xC3], 16, 7, 32, 9)
+        .unwrap();
+
+    let mut bitmap = [0; 6];
+    display
+        .read_image(Ram::BlackWhite, &mut bitmap, 8, 7, 32, 9)
+        .unwrap();
+    assert_eq!(bitmap, [0xFF, 0x0F, 0xF0, 0xFF, 0x3C, 0xC3]);
+
+    // Partial refreshes leave the previous image in the red RAM
+    display
+        .read_image(Ram::Red, &mut bitmap, 8, 7, 32, 9)
+        .unwrap();
+    assert_eq!(bitmap, [0xFF, 0x0F, 0xF0, 0xFF, 0x3C, 0xC3]);
+
+    let result = display.read_image(Ram::BlackWhite, &mut bitmap, 4, 7, 28, 9);
+    assert!(matches!(result, Err(wepd::DisplayError::InvalidBitmap)));
+}
+
+#[test]
+fn read_registers() {
+    let sim = Simulator::new(200, 200);
+    let mut display = Display::new(sim.config()).unwrap();
+    sim.set_user_id(*b"WATCHY-001");
+    sim.set_display_option([1, 2, 3, 4, 5, 6, 7, 8, 9, 10, 11]);
+
+    assert_eq!(display.read_user_id().unwrap(), *b"WATCHY-001");
+    assert_eq!(
+        display.read_display_option().unwrap(),
+        [1, 2, 3, 4, 5, 6, 7, 8, 9, 10, 11]
+    );
+    let status = display.read_status().unwrap();
+    assert_eq!(status.chip_id(), 0b01);
+    assert!(!status.hv_not_ready() && !status.vci_low() && !status.busy());
+}
+
+#[test]
+fn read_registers_wake_hibernating_controller() {
+    let sim = Simulator::new(200, 200);
+    let mut display = Display::new(sim.config()).unwrap();
+    sim.set_user_id(*b"WATCHY-001");
+    display.clear_screen(0xFF).unwrap();
+    display.hibernate().unwrap();
+
+    assert_eq!(display.read_user_id().unwrap(), *b"WATCHY-001");
+    assert!(!sim.is_sleeping());
+    assert_eq!(display.read_status().unwrap().chip_id(), 0b01);
+}
+
+#[test]
+fn diagnose_reports_supply_faults() {
+    let sim = Simulator::new(200, 200);
+    let mut display = Display::new(sim.config()).unwrap();
+
+    let health = display.diagnose().unwrap();
+    assert!(health.responding && health.busy_responsive);
+    assert_eq!((health.booster_ok, health.vci_ok), (Some(true), Some(true)));
+
+    sim.set_hv_ready(false);
+    sim.set_vci_ok(false);
+    let health = display.diagnose().unwrap();
+    assert!(health.responding && health.busy_responsive);
+    assert_eq!(
+        (health.booster_ok, health.vci_ok),
+        (Some(false), Some(false))
+    );
+}
+
+#[test]
+fn diagnose_finds_missing_panel() {
+    let sim = Simulator::new(200, 200);
+    sim.set_connected(false);
+    let mut display = Display::new(sim.config()).unwrap();
+
+    // The busy line stays low, and the data line reads back as all ones
+    let health = display.diagnose().unwrap();
+    assert!(!health.responding && health.busy_responsive);
+    assert_eq!((health.booster_ok, health.vci_ok), (None, None));
+}
+
+#[test]
+fn border_survives_hibernate() {
+    let sim = Simulator::new(200, 200);
+    let mut display = Display::new(sim.config()).unwrap();
+    display.clear_screen(0xFF).unwrap();
+
+    display.set_border(Border::Black).unwrap();
+    assert_eq!(sim.register(0x3c), Some(vec![0x02]));
+
+    display.hibernate().unwrap();
+    display.set_border(Border::HiZ).unwrap();
+    display.clear_screen(0xFF).unwrap();
+    assert_eq!(sim.register(0x3c), Some(vec![0xc0]));
+}
+
+#[test]
+fn set_inverted_changes_only_what_is_shown() {
+    let sim = Simulator::new(200, 200);
+    let mut display = Display::new(sim.config()).unwrap();
+    display.clear_screen(0xFF).unwrap();
+    display.draw_image(&[0x00; 2], 0, 0, 16, 1).unwrap();
+
+    display.set_inverted(true).unwrap();
+    display.draw_image(&[0x0F; 2], 0, 1, 16, 2).unwrap();
+    // Changing the options forces a full refresh, and the RAM stays as it was written
+    assert_eq!(sim.updates().last(), Some(&0xf4));
+    assert!(sim.pixel(0, 0) && sim.pixel(0, 1) && !sim.pixel(4, 1));
+    assert_eq!(sim.bw_ram()[..2], [0x00; 2]);
+    assert!(sim
+        .visible()
+        .iter()
+        .zip(sim.bw_ram())
+        .all(|(&v, r)| v == !r));
+
+    display.draw_image(&[0xFF; 2], 0, 2, 16, 3).unwrap();
+    assert_eq!(sim.updates().last(), Some(&0xfc));
+
+    display.hibernate().unwrap();
+    display.set_inverted(false).unwrap();
+    display.refresh_full().unwrap();
+    assert_eq!(sim.visible(), sim.bw_ram());
+}
+
+#[test]
+fn partial_waveform_is_loaded_for_partial_refreshes() {
+    let sim = Simulator::new(200, 200);
+    let mut display = Display::new(sim.config()).unwrap();
+    display.set_partial_waveform(Some(&Waveform::PARTIAL_FAST));
+    display.clear_screen(0xFF).unwrap();
+    assert_eq!(sim.register(0x32), None);
+
+    display.draw_image(&[0x00; 2], 0, 0, 16, 1).unwrap();
+    assert_eq!(
+        sim.register(0x32).as_deref(),
+        Some(&Waveform::PARTIAL_FAST.lut[..])
+    );
+    assert_eq!(sim.updates().last(), Some(&0xcc));
+}
+
+/// Draws a 16x1 bar, a 16 pixel partial refresh unless the policy asks for a full one, and
+/// returns the display update control value it was shown with.
+fn draw_bar(display: &mut Display<wepd::sim::SimConfiguration>, sim: &Simulator) -> u8 {
+    display.draw_image(&[0x00; 2], 0, 0, 16, 1).unwrap();
+    *sim.updates().last().unwrap()
+}
+
+#[test]
+fn refresh_policy_after_partials() {
+    let sim = Simulator::new(200, 200);
+    let mut display = Display::new(sim.config()).unwrap();
+    display.set_refresh_policy(RefreshPolicy::AfterPartials(2));
+    display.clear_screen(0xFF).unwrap();
+
+    let updates: Vec<u8> = (0..4).map(|_| draw_bar(&mut display, &sim)).collect();
+    assert_eq!(updates, [0xfc, 0xfc, 0xf4, 0xfc]);
+}
+
+#[test]
+fn refresh_policy_after_area() {
+    let sim = Simulator::new(200, 200);
+    let mut display = Display::new(sim.config()).unwrap();
+    display.set_refresh_policy(RefreshPolicy::AfterArea(40));
+    display.clear_screen(0xFF).unwrap();
+
+    let updates: Vec<u8> = (0..4).map(|_| draw_bar(&mut display, &sim)).collect();
+    assert_eq!(updates, [0xfc, 0xfc, 0xf4, 0xfc]);
+}
+
+#[test]
+fn refresh_policy_after_millis() {
+    let sim = Simulator::new(200, 200);
+    let mut display = Display::new(sim.config()).unwrap();
+    display.set_refresh_policy(RefreshPolicy::AfterMillis(1000));
+    display.clear_screen(0xFF).unwrap();
+
+    assert_eq!(draw_bar(&mut display, &sim), 0xfc);
+    display.elapse(999);
+    assert_eq!(draw_bar(&mut display, &sim), 0xfc);
+    display.elapse(1);
+    assert_eq!(draw_bar(&mut display, &sim), 0xf4);
+    assert_eq!(draw_bar(&mut display, &sim), 0xfc);
+}
+
+#[test]
+fn refresh_full_resets_refresh_policy() {
+    let sim = Simulator::new(200, 200);
+    let mut display = Display::new(sim.config()).unwrap();
+    display.set_refresh_policy(RefreshPolicy::AfterPartials(2));
+    display.clear_screen(0xFF).unwrap();
+
+    draw_bar(&mut display, &sim);
+    draw_bar(&mut display, &sim);
+    display.refresh_full().unwrap();
+    assert_eq!(draw_bar(&mut display, &sim), 0xfc);
+}
+
+#[test]
+fn refresh_policy_counts_survive_from_state() {
+    let sim = Simulator::new(200, 200);
+    let mut display = Display::new(sim.config()).unwrap();
+    display.set_refresh_policy(RefreshPolicy::AfterPartials(2));
+    display.clear_screen(0xFF).unwrap();
+    draw_bar(&mut display, &sim);
+    draw_bar(&mut display, &sim);
+    let state = display.state();
+    drop(display);
+
+    let mut display = Display::from_state(sim.config(), state).unwrap();
+    display.set_refresh_policy(RefreshPolicy::AfterPartials(2));
+    assert_eq!(draw_bar(&mut display, &sim), 0xf4);
+}
+
+#[test]
+#[cfg(feature = "embedded-graphics")]
+fn shadow_framebuffer_skips_refresh_when_nothing_changed() {
+    use embedded_graphics_core::{pixelcolor::BinaryColor, prelude::*, primitives::Rectangle};
+
+    let sim = Simulator::new(200, 200);
+    let mut display = Display::new(sim.config()).unwrap();
+    display.clear_screen(0xFF).unwrap();
+    let area = Rectangle::new(Point::new(20, 20), Size::new(40, 10));
+
+    let mut framebuffer = wepd::ShadowFramebuffer::new();
+    framebuffer.fill_solid(&area, BinaryColor::Off).unwrap();
+    framebuffer.flush(&mut display).unwrap();
+    let activations = sim.updates().len();
+
+    // Redrawing the same pixels marks them dirty but changes nothing
+    framebuffer.fill_solid(&area, BinaryColor::Off).unwrap();
+    Pixel(Point::new(20, 20), BinaryColor::Off)
+        .draw(&mut framebuffer)
+        .unwrap();
+    framebuffer.flush(&mut display).unwrap();
+    assert_eq!(sim.updates().len(), activations);
+
+    Pixel(Point::new(100, 100), BinaryColor::Off)
+        .draw(&mut framebuffer)
+        .unwrap();
+    framebuffer.flush(&mut display).unwrap();
+    assert_eq!(sim.updates().len(), activations + 1);
+    assert!(!sim.pixel(100, 100));
+}
+
+#[test]
+fn delay_waiter_times_each_wait() {
+    let mut waiter = DelayWaiter::new(SimDelay)
+        .with_delay_ms(10)
+        .with_timeout_ms(50);
+    for _ in 0..2 {
+        waiter.start_wait();
+        for _ in 0..5 {
+            assert!(waiter.poll_wait().is_ok());
+        }
+        assert!(matches!(waiter.poll_wait(), Err(BusyTimeout)));
+    }
+}
+
+#[test]
+fn border_survives_from_state() {
+    let sim = Simulator::new(200, 200);
+    let mut display = Display::new(sim.config()).unwrap();
+    display.set_border(Border::Black).unwrap();
+    display.clear_screen(0xFF).unwrap();
+    let state = display.state();
+    drop(display);
+
+    let mut display = Display::from_state(sim.config(), state).unwrap();
+    // Something else changed the border since, which setting the controller up again undoes
+    display
+        .send(Command::BorderWaveform(Border::White))
+        .unwrap();
+    display.power_off().unwrap();
+    display.clear_screen(0xFF).unwrap();
+    assert_eq!(sim.register(0x3c), Some(vec![0x02]));
+}
+
+#[test]
+fn inversion_survives_from_state() {
+    let sim = Simulator::new(200, 200);
+    let mut display = Display::new(sim.config()).unwrap();
+    display.clear_screen(0xFF).unwrap();
+    display.set_inverted(true).unwrap();
+    display.refresh_full().unwrap();
+    let state = display.state();
+    drop(display);
+
+    // Already inverted, so nothing needs to be sent or refreshed in full
+    let mut display = Display::from_state(sim.config(), state).unwrap();
+    let updates = sim.updates().len();
+    display.set_inverted(true).unwrap();
+    assert_eq!(sim.updates().len(), updates);
+    display.draw_image(&[0x00; 2], 0, 0, 16, 1).unwrap();
+    assert_eq!(sim.updates()[updates..], [0xfc]);
+
+    display.set_inverted(false).unwrap();
+    assert_eq!(sim.register(0x21), Some(vec![0x00, 0x00]));
+    display.draw_image(&[0xFF; 2], 0, 0, 16, 1).unwrap();
+    assert_eq!(sim.updates().last(), Some(&0xf4));
+    assert_eq!(sim.visible(), sim.bw_ram());
+}
@@ -0,0 +1,75 @@
+#![cfg(feature = "std")]
+
+use wepd::snapshot::Snapshot;
+
+fn checkerboard(width: u16, height: u16) -> Snapshot {
+    let stride = width.div_ceil(8) as usize;
+    let mut data = vec![0xFF; stride * height as usize];
+    for y in 0..height as usize {
+        for x in 0..width as usize {
+            if (x / 4 + y / 4) % 2 == 0 {
+                data[y * stride + x / 8] &= !(0x80 >> (x % 8));
+            }
+        }
+    }
+    Snapshot::new(width, height, data)
+}
+
+#[test]
+fn pbm_round_trip() {
+    let snapshot = checkerboard(13, 7);
+
+    let mut pbm = Vec::new();
+    snapshot.write_pbm(&mut pbm).unwrap();
+    assert!(pbm.starts_with(b"P4\n13 7\n"));
+
+    assert_eq!(Snapshot::read_pbm(&pbm[..]).unwrap(), snapshot);
+}
+
+#[test]
+fn read_pbm_skips_comments() {
+    let pbm = b"P4\n# made by hand\n8 2\n\xF0\x0F";
+    let snapshot = Snapshot::read_pbm(&pbm[..]).unwrap();
+
+    assert!(!snapshot.pixel(0, 0));
+    assert!(snapshot.pixel(7, 0));
+    assert!(snapshot.pixel(0, 1));
+}
+
+#[test]
+fn diff_marks_changed_pixels() {
+    let a = checkerboard(16, 16);
+    let mut data = a.data().to_vec();
+    data[0] ^= 0x01;
+    let b = Snapshot::new(16, 16, data);
+
+    assert_eq!(a.diff(&a), None);
+    let diff = a.diff(&b).unwrap();
+    assert!(!diff.pixel(7, 0));
+    assert!(diff.pixel(6, 0));
+}
+
+#[cfg(feature = "png")]
+#[test]
+fn png_has_header_chunks() {
+    let mut png = Vec::new();
+    checkerboard(200, 200).write_png(&mut png).unwrap();
+
+    assert!(png.starts_with(b"\x89PNG\r\n\x1a\n\0\0\0\x0dIHDR"));
+    assert!(png.ends_with(b"IEND\xae\x42\x60\x82"));
+}
+
+#[cfg(all(feature = "sim", not(feature = "async")))]
+#[test]
+fn simulated_image_matches_golden() {
+    use wepd::{sim::Simulator, Display};
+
+    let sim = Simulator::new(200, 200);
+    let mut display = Display::new(sim.config()).unwrap();
+    display.clear_screen(0xFF).unwrap();
+
+    let sprite = checkerboard(24, 24);
+    display.draw_image(sprite.data(), 37, 50, 61, 74).unwrap();
+
+    Snapshot::from(&sim).assert_golden("tests/golden/sprite.pbm");
+}